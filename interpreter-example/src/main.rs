@@ -12,6 +12,10 @@ enum Symbol {
     Star,
     #[token("/")]
     FSlash,
+    #[token("(")]
+    LeftParen,
+    #[token(")")]
+    RightParen,
 }
 
 #[derive(Lex, Debug, PartialEq, Clone)]
@@ -30,6 +34,7 @@ enum Token {
 
 // -- AST nodes --
 #[derive(Node, Debug, PartialEq)]
+#[delimited(Symbol::LeftParen, Symbol::RightParen)]
 enum Expression {
     #[extract(Literal::Int)]
     Int(i32),
@@ -63,13 +68,22 @@ fn main() {
     println!("Example: `5 + (2 - 4) * 7`");
     loop {
         let mut input = String::new();
-        io::stdin()
+        let read = io::stdin()
             .read_line(&mut input)
             .expect("Failed to read line");
+        if read == 0 {
+            break;
+        }
 
-        let tokens = Token::lex(&input);
-        let ast = Expression::parse(&tokens).unwrap();
+        let lexed = Token::lex_spanned(&input);
+        for error in &lexed.errors {
+            println!("Lex error: {error}");
+        }
+        let tokens = lexed.into_values();
         println!("Tokens: {tokens:?}");
-        println!("AST: {ast}");
+        match Expression::try_parse(&tokens) {
+            Ok(ast) => println!("AST: {ast}"),
+            Err(error) => println!("Error: {error}"),
+        }
     }
 }
@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+
+use proc_macro_error::emit_error;
+use regex::bytes::Regex;
+use regex_automata::dfa::{Automaton, StartKind, dense};
+use regex_automata::{Anchored, Input, MatchKind};
+use regex_syntax::hir::HirKind;
+
+use crate::lex::{ArmMatcher, LexArm, is_word_token};
+
+/// Reports patterns that can never produce their variant because an arm tried before them
+/// always wins, and pairs of patterns that match the same text with the same priority.
+///
+/// Only patterns declared on the enum itself are analyzed here. Variants that delegate to another
+/// `Lex` type can't be seen until that type is compiled, so they're checked by the test
+/// `derive(Lex)` generates from `Lex::patterns`, which `rcket::lex::check` runs the same checks on.
+pub(crate) fn check_arms(arms: &[LexArm]) {
+    for (index, arm) in arms.iter().enumerate() {
+        let Some(text) = literal_text(&arm.matcher) else {
+            continue;
+        };
+        let span = match &arm.matcher {
+            ArmMatcher::Token(lit) | ArmMatcher::Regex { lit, .. } => lit.span(),
+            ArmMatcher::Wrapped(_) | ArmMatcher::Opaque => continue,
+        };
+
+        let winner = arms[..index]
+            .iter()
+            .filter(|earlier| earlier.variant_name != arm.variant_name && !earlier.conditional)
+            .find_map(|earlier| Some((earlier, match_len(&earlier.matcher, &text)?)));
+
+        if let Some((earlier, length)) = winner {
+            if length == text.len() && earlier.priority == arm.priority {
+                emit_error!(
+                    span,
+                    "`{}` and `{}` both match {:?} with the same length and priority, `{}` always wins",
+                    earlier.variant_name,
+                    arm.variant_name,
+                    text,
+                    earlier.variant_name
+                );
+            } else {
+                emit_error!(
+                    span,
+                    "pattern {:?} of `{}` is unreachable, it is always lexed as `{}`",
+                    text,
+                    arm.variant_name,
+                    earlier.variant_name
+                );
+            }
+        }
+    }
+
+    for (index, arm) in arms.iter().enumerate() {
+        let ArmMatcher::Regex { lit, .. } = &arm.matcher else {
+            continue;
+        };
+        if literal_text(&arm.matcher).is_some() {
+            continue;
+        }
+        let covering = arms[..index].iter().find_map(|earlier| {
+            if earlier.variant_name == arm.variant_name || earlier.conditional {
+                return None;
+            }
+            let ArmMatcher::Regex {
+                lit: earlier_lit,
+                word_boundary,
+            } = &earlier.matcher
+            else {
+                return None;
+            };
+            if earlier_lit.value() == lit.value() {
+                return Some((earlier, Coverage::Same));
+            }
+            // an earlier pattern that has to end at a word boundary can give way to a later one
+            // matching a different length
+            if *word_boundary {
+                return None;
+            }
+            Some((earlier, coverage(&earlier_lit.value(), &lit.value())?))
+        });
+        match covering {
+            Some((earlier, Coverage::Same)) => emit_error!(
+                lit.span(),
+                "`{}` and `{}` use the same pattern {:?}, `{}` always wins",
+                earlier.variant_name,
+                arm.variant_name,
+                lit.value(),
+                earlier.variant_name
+            ),
+            Some((earlier, Coverage::Equal)) => emit_error!(
+                lit.span(),
+                "`{}` and `{}` match the same text, `{}` always wins",
+                earlier.variant_name,
+                arm.variant_name,
+                earlier.variant_name
+            ),
+            Some((earlier, Coverage::Wider)) => emit_error!(
+                lit.span(),
+                "pattern {:?} of `{}` is unreachable, everything it matches is lexed as `{}`",
+                lit.value(),
+                arm.variant_name,
+                earlier.variant_name
+            ),
+            None => {}
+        }
+    }
+}
+
+/// How an earlier regex covers a later one that can never win over it.
+enum Coverage {
+    /// The patterns are written the same.
+    Same,
+    /// They're written differently but match the same text.
+    Equal,
+    /// The earlier one matches everything the later one does and more.
+    Wider,
+}
+
+/// Whether `earlier` matches every text `later` does, found by walking their DFAs side by side
+/// over every byte. `None` when it doesn't, or when either can't be built as a DFA, like with
+/// Unicode word boundaries.
+fn coverage(earlier: &str, later: &str) -> Option<Coverage> {
+    let build = |pattern: &str| {
+        dense::Builder::new()
+            .configure(
+                dense::Config::new()
+                    .match_kind(MatchKind::All)
+                    .start_kind(StartKind::Anchored),
+            )
+            .build(pattern)
+            .ok()
+    };
+    let (earlier_dfa, later_dfa) = (build(earlier)?, build(later)?);
+    let input = Input::new("").anchored(Anchored::Yes);
+    let start = (
+        earlier_dfa.start_state_forward(&input).ok()?,
+        later_dfa.start_state_forward(&input).ok()?,
+    );
+
+    let mut seen = HashSet::from([start]);
+    let mut pending = vec![start];
+    let mut equal = true;
+    while let Some((earlier_state, later_state)) = pending.pop() {
+        // matches are reported a byte late, so whether the text so far matches shows at its end
+        let earlier_matches = earlier_dfa.is_match_state(earlier_dfa.next_eoi_state(earlier_state));
+        let later_matches = later_dfa.is_match_state(later_dfa.next_eoi_state(later_state));
+        if later_matches && !earlier_matches {
+            return None;
+        }
+        equal &= earlier_matches == later_matches;
+        if later_dfa.is_dead_state(later_state) && !equal {
+            continue;
+        }
+        for byte in 0..=u8::MAX {
+            let next = (
+                earlier_dfa.next_state(earlier_state, byte),
+                later_dfa.next_state(later_state, byte),
+            );
+            if seen.insert(next) {
+                pending.push(next);
+            }
+        }
+    }
+    Some(if equal {
+        Coverage::Equal
+    } else {
+        Coverage::Wider
+    })
+}
+
+/// The exact text an arm matches, if it only ever matches one string.
+pub(crate) fn literal_text(matcher: &ArmMatcher) -> Option<String> {
+    match matcher {
+        ArmMatcher::Token(lit) => Some(lit.value()),
+        ArmMatcher::Regex { lit, .. } => {
+            let hir = regex_syntax::Parser::new().parse(&lit.value()).ok()?;
+            match hir.kind() {
+                HirKind::Literal(literal) => String::from_utf8(literal.0.to_vec()).ok(),
+                _ => None,
+            }
+        }
+        ArmMatcher::Wrapped(_) | ArmMatcher::Opaque => None,
+    }
+}
+
+/// How much of `text` the generated arm would consume, mirroring the runtime checks.
+fn match_len(matcher: &ArmMatcher, text: &str) -> Option<usize> {
+    let (length, word_boundary) = match matcher {
+        ArmMatcher::Token(lit) => {
+            let value = lit.value();
+            if !text.starts_with(&value) {
+                return None;
+            }
+            (value.len(), is_word_token(&value))
+        }
+        ArmMatcher::Regex { lit, word_boundary } => {
+            let regex = Regex::new(&format!("^(?:{})", lit.value())).ok()?;
+            (regex.find(text.as_bytes())?.end(), *word_boundary)
+        }
+        ArmMatcher::Wrapped(_) | ArmMatcher::Opaque => return None,
+    };
+    let rest = text.get(length..)?;
+    let at_boundary =
+        !rest.starts_with(|character: char| character.is_alphanumeric() || character == '_');
+    (length > 0 && (!word_boundary || at_boundary)).then_some(length)
+}
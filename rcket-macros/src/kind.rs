@@ -0,0 +1,288 @@
+use proc_macro_error::emit_error;
+use quote::{format_ident, quote};
+use syn::{
+    DataEnum, DeriveInput, Fields, Ident, LitStr, Path, PathArguments, Type, Variant,
+    punctuated::Punctuated,
+};
+
+use crate::lex::{LexPattern, LexPatternKind, VariantOptions, is_error_variant};
+use crate::node::single_unnamed_field;
+
+/// Generates `{Type}Kind`, a copyable enum with one variant per token, plus `kind()` on the token
+/// enum and a human-readable `name()` on the kind. The hidden `{Type}Kinds` struct holds the kinds
+/// of the enum's own variants in fields named after them, so `derive(Node)` can name them with
+/// only the token path.
+///
+/// Variants wrapping another `Lex` enum nest that enum's kind, so `Token::Symbol(Symbol::Plus)`
+/// has the kind `TokenKind::Symbol(SymbolKind::Plus)`.
+pub(crate) fn derive_kind(
+    input: &DeriveInput,
+    data_enum: &DataEnum,
+    delimiters: &[(Ident, Ident)],
+) -> proc_macro2::TokenStream {
+    let type_name = &input.ident;
+    let visibility = &input.vis;
+    let kind_name = kind_ident(type_name);
+    let kinds_name = format_ident!("{}Kinds", type_name);
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut kind_variants = vec![];
+    let mut kind_arms = vec![];
+    let mut name_arms = vec![];
+    let mut closing_kind_arms = vec![];
+    let mut is_closing_arms = vec![];
+    let mut own_kinds = vec![];
+
+    for variant in &data_enum.variants {
+        let variant_name = &variant.ident;
+        let wrapped_kind = (!has_pattern_attributes(variant) && !is_error_variant(variant))
+            .then(|| single_unnamed_field(variant).and_then(inner_kind_path))
+            .flatten();
+
+        if let Some(inner_kind) = wrapped_kind {
+            kind_variants.push(quote! { #variant_name(#inner_kind) });
+            kind_arms.push(
+                quote! { Self::#variant_name(inner) => #kind_name::#variant_name(inner.kind()), },
+            );
+            name_arms.push(quote! { Self::#variant_name(inner) => inner.name(), });
+            closing_kind_arms.push(quote! {
+                Self::#variant_name(inner) => ::rcket::tree::Delimiters::closing_kind(inner).map(#kind_name::#variant_name),
+            });
+            is_closing_arms.push(quote! {
+                Self::#variant_name(inner) => ::rcket::tree::Delimiters::is_closing(inner),
+            });
+            continue;
+        }
+
+        let pattern = match &variant.fields {
+            Fields::Unit => quote! { Self::#variant_name },
+            Fields::Unnamed(_) => quote! { Self::#variant_name(..) },
+            Fields::Named(_) => quote! { Self::#variant_name { .. } },
+        };
+        let name = display_name(variant);
+        kind_variants.push(quote! { #variant_name });
+        kind_arms.push(quote! { #pattern => #kind_name::#variant_name, });
+        name_arms.push(quote! { Self::#variant_name => #name, });
+        own_kinds.push(variant_name);
+    }
+
+    for (open, close) in delimiters {
+        let mut known = true;
+        for name in [open, close] {
+            if !data_enum
+                .variants
+                .iter()
+                .any(|variant| variant.ident == *name)
+            {
+                emit_error!(name, "no variant named `{}` to use as a delimiter", name);
+                known = false;
+            }
+        }
+        if known {
+            closing_kind_arms.push(quote! { Self::#open { .. } => Some(#kind_name::#close), });
+            is_closing_arms.push(quote! { Self::#close { .. } => true, });
+        }
+    }
+
+    quote! {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #visibility enum #kind_name {
+            #(#kind_variants),*
+        }
+
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        #visibility struct #kinds_name {
+            #(pub #own_kinds: #kind_name),*
+        }
+
+        impl #kind_name {
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(#name_arms)*
+                }
+            }
+        }
+
+        impl ::std::fmt::Display for #kind_name {
+            fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                formatter.write_str(self.name())
+            }
+        }
+
+        impl #impl_generics #type_name #type_generics #where_clause {
+            pub fn kind(&self) -> #kind_name {
+                match self {
+                    #(#kind_arms)*
+                }
+            }
+        }
+
+        impl #impl_generics ::rcket::tree::Delimiters for #type_name #type_generics #where_clause {
+            #[allow(unreachable_patterns)]
+            fn closing_kind(&self) -> Option<#kind_name> {
+                match self {
+                    #(#closing_kind_arms)*
+                    _ => None,
+                }
+            }
+
+            #[allow(unreachable_patterns)]
+            fn is_closing(&self) -> bool {
+                match self {
+                    #(#is_closing_arms)*
+                    _ => false,
+                }
+            }
+        }
+
+        impl #impl_generics ::rcket::HasKind for #type_name #type_generics #where_clause {
+            type Kind = #kind_name;
+            fn kind(&self) -> #kind_name {
+                #type_name::kind(self)
+            }
+
+            type Kinds = #kinds_name;
+            fn kinds() -> #kinds_name {
+                #kinds_name {
+                    #(#own_kinds: #kind_name::#own_kinds),*
+                }
+            }
+        }
+    }
+}
+
+/// Implements `Project` and `ProjectKind` for the enum itself and for each enum a variant wraps, so nodes can match
+/// nested tokens without knowing the wrapper's variant names.
+pub(crate) fn derive_project(
+    input: &DeriveInput,
+    data_enum: &DataEnum,
+) -> proc_macro2::TokenStream {
+    let type_name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let mut wrapped_types: Vec<(String, &Ident)> = vec![];
+    let kind_name = kind_ident(type_name);
+    let mut impls = vec![quote! {
+        impl #impl_generics ::rcket::Project<#type_name #type_generics> for #type_name #type_generics #where_clause {
+            fn project(&self) -> Option<&#type_name #type_generics> {
+                Some(self)
+            }
+        }
+
+        impl #impl_generics ::rcket::ProjectKind<#type_name #type_generics> for #type_name #type_generics #where_clause {
+            fn project_kind(kind: #kind_name) -> #kind_name {
+                kind
+            }
+        }
+    }];
+
+    for variant in &data_enum.variants {
+        if has_pattern_attributes(variant) || is_error_variant(variant) {
+            continue;
+        }
+        let Some(inner_type) = single_unnamed_field(variant) else {
+            continue;
+        };
+        // `Project<T>` can only lead to one of the variants, and the second one is never lexed
+        let variant_name = &variant.ident;
+        let type_string = quote! { #inner_type }.to_string();
+        if let Some((_, earlier)) = wrapped_types
+            .iter()
+            .find(|(wrapped, _)| *wrapped == type_string)
+        {
+            emit_error!(
+                inner_type,
+                "`{}` is already wrapped by the variant `{}`, only one variant can wrap each token enum",
+                type_string.replace(' ', ""),
+                earlier
+            );
+            continue;
+        }
+        wrapped_types.push((type_string, variant_name));
+        impls.push(quote! {
+            impl #impl_generics ::rcket::Project<#inner_type> for #type_name #type_generics #where_clause {
+                #[allow(unreachable_patterns)]
+                fn project(&self) -> Option<&#inner_type> {
+                    match self {
+                        Self::#variant_name(inner) => Some(inner),
+                        _ => None,
+                    }
+                }
+            }
+
+            impl #impl_generics ::rcket::ProjectKind<#inner_type> for #type_name #type_generics #where_clause {
+                fn project_kind(kind: <#inner_type as ::rcket::HasKind>::Kind) -> #kind_name {
+                    #kind_name::#variant_name(kind)
+                }
+            }
+        });
+    }
+
+    quote! { #(#impls)* }
+}
+
+fn kind_ident(type_name: &Ident) -> Ident {
+    format_ident!("{}Kind", type_name)
+}
+
+/// `Literal<'a>` is a `Lex` enum, so its kind is `LiteralKind`.
+fn inner_kind_path(inner_type: &Type) -> Option<Path> {
+    let Type::Path(type_path) = inner_type else {
+        return None;
+    };
+    let mut path = type_path.path.clone();
+    let last_segment = path.segments.last_mut()?;
+    last_segment.ident = kind_ident(&last_segment.ident);
+    last_segment.arguments = PathArguments::None;
+    Some(path)
+}
+
+fn has_pattern_attributes(variant: &Variant) -> bool {
+    variant.attrs.iter().any(|attribute| {
+        attribute.path().is_ident("token")
+            || attribute.path().is_ident("regex")
+            || attribute.path().is_ident("seq")
+            || attribute.path().is_ident("choice")
+            || attribute.path().is_ident("matcher")
+    })
+}
+
+/// `#[lex(name = "...")]` if given, then the first `#[token]` literal quoted, then the variant
+/// name split into lowercase words.
+fn display_name(variant: &Variant) -> String {
+    match VariantOptions::from_attributes(&variant.attrs) {
+        Ok(VariantOptions {
+            name: Some(name), ..
+        }) => return name,
+        Ok(_) => {}
+        Err(error) => emit_error!(error.span(), "{}", error),
+    }
+
+    let first_token = variant.attrs.iter().find_map(|attribute| {
+        if attribute.path().is_ident("token") {
+            attribute.parse_args::<LitStr>().ok()
+        } else if attribute.path().is_ident("choice") {
+            let patterns = attribute
+                .parse_args_with(Punctuated::<LexPattern, syn::Token![,]>::parse_terminated)
+                .ok()?;
+            patterns
+                .into_iter()
+                .find(|pattern| matches!(pattern.kind, LexPatternKind::Token))
+                .map(|pattern| pattern.lit)
+        } else {
+            None
+        }
+    });
+    if let Some(lit) = first_token {
+        return format!("'{}'", lit.value());
+    }
+
+    let mut words = String::new();
+    for character in variant.ident.to_string().chars() {
+        if character.is_uppercase() && !words.is_empty() {
+            words.push(' ');
+        }
+        words.extend(character.to_lowercase());
+    }
+    words
+}
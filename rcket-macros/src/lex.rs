@@ -1,14 +1,19 @@
 use proc_macro::TokenStream;
-use quote::{format_ident, quote};
+use proc_macro_error::{abort, emit_error};
+use quote::{format_ident, quote, quote_spanned};
 use syn::{
-    Data, DataEnum, DeriveInput, Ident, LitStr, Type, parse_macro_input, punctuated::Punctuated,
+    Attribute, Data, DataEnum, DeriveInput, GenericArgument, GenericParam, Generics, Ident,
+    Lifetime, LifetimeParam, LitByteStr, LitInt, LitStr, Path, PathArguments, Type, Variant,
+    WherePredicate, parse_macro_input, parse_quote, punctuated::Punctuated, spanned::Spanned,
 };
 
-use crate::node::single_unnamed_field;
+use crate::check;
+use crate::kind;
+use crate::node::{mentions_type_parameter, single_unnamed_field};
 
 pub(crate) struct LexPattern {
-    kind: LexPatternKind,
-    lit: LitStr,
+    pub(crate) kind: LexPatternKind,
+    pub(crate) lit: LitStr,
 }
 
 pub(crate) enum LexPatternKind {
@@ -16,6 +21,23 @@ pub(crate) enum LexPatternKind {
     Regex,
 }
 
+/// The argument of a lookaround attribute, either a plain string or `token(..)`/`regex(..)`.
+struct LexCondition(LexPattern);
+
+impl syn::parse::Parse for LexCondition {
+    fn parse(parse_stream: syn::parse::ParseStream) -> syn::Result<Self> {
+        if parse_stream.peek(LitStr) {
+            let lit: LitStr = parse_stream.parse()?;
+            Ok(LexCondition(LexPattern {
+                kind: LexPatternKind::Token,
+                lit,
+            }))
+        } else {
+            Ok(LexCondition(parse_stream.parse()?))
+        }
+    }
+}
+
 impl syn::parse::Parse for LexPattern {
     fn parse(parse_stream: syn::parse::ParseStream) -> syn::Result<Self> {
         let identifier: Ident = parse_stream.parse()?;
@@ -31,140 +53,745 @@ impl syn::parse::Parse for LexPattern {
     }
 }
 
+/// Enum-level `#[lex(...)]` options.
+#[derive(Default)]
+pub(crate) struct LexOptions {
+    check: bool,
+    bytes: bool,
+    /// `delimiters(LeftParen = RightParen, ...)` pairs of opening and closing variants.
+    delimiters: Vec<(Ident, Ident)>,
+}
+
+impl LexOptions {
+    fn from_attributes(attributes: &[Attribute]) -> Self {
+        let mut options = LexOptions::default();
+        for attribute in attributes {
+            if !attribute.path().is_ident("lex") {
+                continue;
+            }
+            let result = attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("check") {
+                    options.check = true;
+                    Ok(())
+                } else if meta.path.is_ident("bytes") {
+                    options.bytes = true;
+                    Ok(())
+                } else if meta.path.is_ident("delimiters") {
+                    meta.parse_nested_meta(|pair| {
+                        let open = pair.path.require_ident()?.clone();
+                        let close: Ident = pair.value()?.parse()?;
+                        options.delimiters.push((open, close));
+                        Ok(())
+                    })
+                } else {
+                    Err(meta.error(
+                        "unknown lex option, expected `check`, `bytes` or `delimiters(...)`",
+                    ))
+                }
+            });
+            if let Err(error) = result {
+                emit_error!(error.span(), "{}", error);
+            }
+        }
+        options
+    }
+}
+
+/// Variant-level `#[lex(...)]` options.
+#[derive(Default)]
+pub(crate) struct VariantOptions {
+    pub(crate) name: Option<String>,
+    pub(crate) priority: Option<usize>,
+}
+
+impl VariantOptions {
+    /// Errors are reported by the kind derive, which reads the name, so they only show up once.
+    pub(crate) fn from_attributes(attributes: &[Attribute]) -> syn::Result<Self> {
+        let mut options = VariantOptions::default();
+        for attribute in attributes {
+            if !attribute.path().is_ident("lex") {
+                continue;
+            }
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    options.name = Some(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                } else if meta.path.is_ident("priority") {
+                    options.priority = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                    Ok(())
+                } else {
+                    Err(meta
+                        .error("unknown lex option, expected `name = \"...\"` or `priority = ...`"))
+                }
+            })?;
+        }
+        Ok(options)
+    }
+}
+
 pub(crate) fn derive_lex(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let type_name = &input.ident;
+    let Data::Enum(data_enum) = &input.data else {
+        abort!(type_name, "`Lex` can only be derived for enums");
+    };
+    let options = LexOptions::from_attributes(&input.attrs);
 
-    let lex_one_body = match &input.data {
-        Data::Enum(data_enum) => derive_lex_enum(data_enum),
-        _ => quote! { todo!() },
+    // tokens borrow from the input through the enum's own lifetime, owned enums get a fresh one
+    let mut impl_generics = input.generics.clone();
+    let lifetime = match input.generics.lifetimes().next() {
+        Some(lifetime_param) => lifetime_param.lifetime.clone(),
+        None => {
+            let lifetime = Lifetime::new("'src", proc_macro2::Span::call_site());
+            impl_generics.params.insert(
+                0,
+                GenericParam::Lifetime(LifetimeParam::new(lifetime.clone())),
+            );
+            lifetime
+        }
     };
+    let mode = InputMode {
+        lifetime,
+        bytes: options.bytes,
+    };
+    let bounds = field_bounds(data_enum, &input.generics, &mode);
+    impl_generics.make_where_clause().predicates.extend(bounds);
+    let (impl_generics, _, where_clause) = impl_generics.split_for_impl();
+    let (_, type_generics, _) = input.generics.split_for_impl();
+
+    let (lex_methods, wraps) = derive_lex_enum(type_name, data_enum, &options, &mode);
+    let kind_impl = kind::derive_kind(&input, data_enum, &options.delimiters);
+    let project_impl = kind::derive_project(&input, data_enum);
+    let check_test = (options.check && wraps)
+        .then(|| check_test(&input, &mode))
+        .flatten();
 
+    let lex_trait = mode.lex_trait();
     quote! {
-        impl ::rcket::Lex for #type_name {
-            fn lex_one(input: &str) -> Option<(Self, &str)> {
-                #lex_one_body
-            }
+        impl #impl_generics #lex_trait for #type_name #type_generics #where_clause {
+            #lex_methods
         }
+        #kind_impl
+        #project_impl
+        #check_test
     }
     .into()
 }
 
-fn derive_lex_enum(data_enum: &DataEnum) -> proc_macro2::TokenStream {
-    let mut arm_pairs: Vec<(usize, proc_macro2::TokenStream)> = vec![];
+/// A test running `#[lex(check)]` on the patterns of an enum wrapping other `Lex` enums, which
+/// are only known once those are compiled. Enums with type parameters have no type to test.
+fn check_test(input: &DeriveInput, mode: &InputMode) -> Option<proc_macro2::TokenStream> {
+    if input.generics.type_params().next().is_some()
+        || input.generics.const_params().next().is_some()
+    {
+        return None;
+    }
+    let type_name = &input.ident;
+    let lifetime = Lifetime::new("'static", proc_macro2::Span::call_site());
+    let lifetimes = input.generics.lifetimes().map(|_| &lifetime);
+    let lex_trait = InputMode {
+        lifetime: lifetime.clone(),
+        bytes: mode.bytes,
+    }
+    .lex_trait();
+    let test_name = format_ident!("{}_patterns_are_reachable", type_name);
+    Some(quote! {
+        #[cfg(test)]
+        #[test]
+        #[allow(non_snake_case)]
+        fn #test_name() {
+            let patterns = <#type_name<#(#lifetimes),*> as #lex_trait>::patterns();
+            let conflicts = ::rcket::lex::check::conflicts(&patterns);
+            assert!(conflicts.is_empty(), "{}", conflicts.join("\n"));
+        }
+    })
+}
+
+/// What the generated `try_lex_after` reads, `&'src str` by default or `&'src [u8]` with `#[lex(bytes)]`.
+pub(crate) struct InputMode {
+    lifetime: Lifetime,
+    pub(crate) bytes: bool,
+}
+
+impl InputMode {
+    fn input_type(&self) -> proc_macro2::TokenStream {
+        let lifetime = &self.lifetime;
+        if self.bytes {
+            quote! { &#lifetime [u8] }
+        } else {
+            quote! { &#lifetime str }
+        }
+    }
+
+    fn lex_trait(&self) -> proc_macro2::TokenStream {
+        let lifetime = &self.lifetime;
+        if self.bytes {
+            quote! { ::rcket::Lex<#lifetime, [u8]> }
+        } else {
+            quote! { ::rcket::Lex<#lifetime> }
+        }
+    }
+
+    fn regex_type(&self) -> proc_macro2::TokenStream {
+        if self.bytes {
+            quote! { ::rcket::regex::bytes::Regex }
+        } else {
+            quote! { ::rcket::regex::Regex }
+        }
+    }
+
+    /// A `&'static` regex compiled from `lit` the first time it's used, so matching a token
+    /// doesn't recompile its pattern on every attempt.
+    fn cached_regex(&self, lit: &LitStr) -> proc_macro2::TokenStream {
+        let regex_type = self.regex_type();
+        quote! {
+            {
+                static REGEX: ::std::sync::LazyLock<#regex_type> =
+                    ::std::sync::LazyLock::new(|| #regex_type::new(#lit).unwrap());
+                &*REGEX
+            }
+        }
+    }
+
+    fn literal(&self, lit: &LitStr) -> proc_macro2::TokenStream {
+        if self.bytes {
+            let byte_lit = LitByteStr::new(lit.value().as_bytes(), lit.span());
+            quote! { #byte_lit }
+        } else {
+            quote! { #lit }
+        }
+    }
+
+    /// Whether `rest` doesn't continue the identifier a word pattern just matched.
+    fn at_word_boundary(&self, rest: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        if self.bytes {
+            quote! { !matches!(#rest.first(), Some(byte) if byte.is_ascii_alphanumeric() || *byte == b'_') }
+        } else {
+            quote! { (#rest.is_empty() || !#rest.starts_with(|character: char| character.is_alphanumeric() || character == '_')) }
+        }
+    }
+
+    /// Converts matched text into a `Result<field_type, String>`. `&str` and `&[u8]` fields borrow
+    /// it, `Vec<u8>` copies the bytes and anything else goes through `FromStr`, whose error message
+    /// is kept for the diagnostic.
+    fn field_value(
+        &self,
+        field_type: &Type,
+        text: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        if !self.bytes {
+            if is_reference_to(field_type, "str") {
+                quote! { Ok::<_, String>(#text) }
+            } else {
+                quote! { (#text).parse::<#field_type>().map_err(|error| error.to_string()) }
+            }
+        } else if is_byte_slice_reference(field_type) {
+            quote! { Ok::<_, String>(#text) }
+        } else if is_byte_vec(field_type) {
+            quote! { Ok::<_, String>((#text).to_vec()) }
+        } else if is_reference_to(field_type, "str") {
+            quote! { ::std::str::from_utf8(#text).map_err(|error| error.to_string()) }
+        } else {
+            quote! {
+                ::std::str::from_utf8(#text)
+                    .map_err(|error| error.to_string())
+                    .and_then(|text| text.parse::<#field_type>().map_err(|error| error.to_string()))
+            }
+        }
+    }
+}
+
+/// A generated lexing arm together with what the ambiguity checks need to know about it.
+pub(crate) struct LexArm {
+    pub(crate) priority: usize,
+    pub(crate) variant_name: Ident,
+    pub(crate) matcher: ArmMatcher,
+    code: proc_macro2::TokenStream,
+    /// Lookaround conditions can reject a match, so the arm doesn't always win.
+    pub(crate) conditional: bool,
+    /// How far the arm got before failing, for arms that can fail halfway like unterminated strings.
+    partial_len: Option<proc_macro2::TokenStream>,
+}
+
+pub(crate) enum ArmMatcher {
+    Token(LitStr),
+    Regex {
+        lit: LitStr,
+        word_boundary: bool,
+    },
+    /// A variant wrapping another `Lex` enum, which has patterns of its own.
+    Wrapped(Box<Type>),
+    Opaque,
+}
+
+/// The methods of the `Lex` impl, and whether the enum wraps other `Lex` enums.
+fn derive_lex_enum(
+    type_name: &Ident,
+    data_enum: &DataEnum,
+    options: &LexOptions,
+    mode: &InputMode,
+) -> (proc_macro2::TokenStream, bool) {
+    let mut arms: Vec<LexArm> = vec![];
+    let mut error_variant = None;
 
     for variant in &data_enum.variants {
         let variant_name = &variant.ident;
+        if is_error_variant(variant) {
+            if error_variant.is_some() {
+                emit_error!(variant, "only one variant can be marked `#[error]`");
+            }
+            error_variant = Some(variant);
+            continue;
+        }
+
+        let guard = variant_guard(variant, mode);
+        let conditional = guard.is_some();
+        let guard = guard.unwrap_or_else(|| quote! { true });
+        let priority_override = VariantOptions::from_attributes(&variant.attrs)
+            .unwrap_or_default()
+            .priority;
+        let mut push_arm =
+            |priority: usize,
+             matcher: ArmMatcher,
+             code: proc_macro2::TokenStream,
+             partial_len: Option<proc_macro2::TokenStream>| {
+                arms.push(LexArm {
+                    priority: priority_override.unwrap_or(priority),
+                    variant_name: variant_name.clone(),
+                    matcher,
+                    code,
+                    conditional,
+                    partial_len,
+                })
+            };
         let has_attributes = variant.attrs.iter().any(|attribute| {
             attribute.path().is_ident("token")
                 || attribute.path().is_ident("regex")
                 || attribute.path().is_ident("seq")
                 || attribute.path().is_ident("choice")
+                || attribute.path().is_ident("matcher")
         });
 
         if has_attributes {
             for attribute in &variant.attrs {
                 if attribute.path().is_ident("token") {
                     if let Ok(lit) = attribute.parse_args::<LitStr>() {
-                        arm_pairs.push((lit.value().len(), token_lex_arm(variant_name, &lit)));
+                        reject_empty_pattern(&LexPatternKind::Token, &lit, mode);
+                        push_arm(
+                            lit.value().len(),
+                            ArmMatcher::Token(lit.clone()),
+                            token_lex_arm(variant_name, &lit, &guard, mode),
+                            None,
+                        );
                     }
                 } else if attribute.path().is_ident("regex") {
                     if let Ok(lit) = attribute.parse_args::<LitStr>() {
-                        arm_pairs.push((
+                        reject_empty_pattern(&LexPatternKind::Regex, &lit, mode);
+                        let field_type = single_unnamed_field(variant);
+                        push_arm(
                             lit.value().len(),
-                            regex_lex_arm(variant_name, &lit, single_unnamed_field(variant)),
-                        ));
+                            ArmMatcher::Regex {
+                                lit: lit.clone(),
+                                word_boundary: field_type.is_none(),
+                            },
+                            regex_lex_arm(variant_name, &lit, field_type, &guard, mode),
+                            None,
+                        );
                     }
                 } else if attribute.path().is_ident("seq") {
                     if let Ok(patterns) = attribute
                         .parse_args_with(Punctuated::<LexPattern, syn::Token![,]>::parse_terminated)
                     {
                         let patterns_vec: Vec<LexPattern> = patterns.into_iter().collect();
-                        arm_pairs.push((
+                        let empty_steps: Vec<bool> = patterns_vec
+                            .iter()
+                            .map(|pattern| pattern_matches_empty(&pattern.kind, &pattern.lit, mode))
+                            .collect();
+                        if empty_steps.iter().all(|&can_be_empty| can_be_empty) {
+                            emit_error!(
+                                attribute,
+                                "every step of this sequence can match the empty string"
+                            );
+                        }
+                        push_arm(
                             0,
-                            seq_lex_arm(variant_name, &patterns_vec, single_unnamed_field(variant)),
-                        ));
+                            ArmMatcher::Opaque,
+                            seq_lex_arm(
+                                variant_name,
+                                &patterns_vec,
+                                single_unnamed_field(variant),
+                                &guard,
+                                mode,
+                            ),
+                            Some(seq_partial_len(&patterns_vec, mode)),
+                        );
+                    }
+                } else if attribute.path().is_ident("matcher") {
+                    match attribute.parse_args::<Path>() {
+                        Ok(path) => push_arm(
+                            0,
+                            ArmMatcher::Opaque,
+                            matcher_lex_arm(
+                                variant_name,
+                                &path,
+                                single_unnamed_field(variant),
+                                &guard,
+                                mode,
+                            ),
+                            None,
+                        ),
+                        Err(error) => emit_error!(error.span(), "{}", error),
                     }
                 } else if attribute.path().is_ident("choice")
                     && let Ok(patterns) = attribute
                         .parse_args_with(Punctuated::<LexPattern, syn::Token![,]>::parse_terminated)
-                    {
-                        for pattern in patterns.iter() {
-                            let arm = match pattern.kind {
-                                LexPatternKind::Token => token_lex_arm(variant_name, &pattern.lit),
-                                LexPatternKind::Regex => regex_lex_arm(
-                                    variant_name,
-                                    &pattern.lit,
-                                    single_unnamed_field(variant),
-                                ),
-                            };
-                            arm_pairs.push((pattern.lit.value().len(), arm));
-                        }
+                {
+                    for pattern in patterns.iter() {
+                        reject_empty_pattern(&pattern.kind, &pattern.lit, mode);
+                        let field_type = single_unnamed_field(variant);
+                        let (matcher, arm) = match pattern.kind {
+                            LexPatternKind::Token => (
+                                ArmMatcher::Token(pattern.lit.clone()),
+                                token_lex_arm(variant_name, &pattern.lit, &guard, mode),
+                            ),
+                            LexPatternKind::Regex => (
+                                ArmMatcher::Regex {
+                                    lit: pattern.lit.clone(),
+                                    word_boundary: field_type.is_none(),
+                                },
+                                regex_lex_arm(variant_name, &pattern.lit, field_type, &guard, mode),
+                            ),
+                        };
+                        push_arm(pattern.lit.value().len(), matcher, arm, None);
                     }
+                }
             }
         } else if let Some(inner_type) = single_unnamed_field(variant) {
-            arm_pairs.push((0, bare_lex_arm(variant_name, inner_type)));
+            let lex_trait = mode.lex_trait();
+            push_arm(
+                0,
+                ArmMatcher::Wrapped(Box::new(inner_type.clone())),
+                bare_lex_arm(variant_name, inner_type, &guard, mode),
+                Some(quote! { <#inner_type as #lex_trait>::partial_match_len(input) }),
+            );
         }
     }
 
     // stable sort descending so longer patterns are tried first
-    arm_pairs.sort_by(|first, second| second.0.cmp(&first.0));
+    arms.sort_by_key(|arm| std::cmp::Reverse(arm.priority));
 
-    let arms: Vec<proc_macro2::TokenStream> = arm_pairs.into_iter().map(|(_, arm)| arm).collect();
-    quote! { #(#arms)* None }
-}
+    if options.check {
+        check::check_arms(&arms);
+    }
+    let wraps = arms
+        .iter()
+        .any(|arm| matches!(arm.matcher, ArmMatcher::Wrapped(_)));
+    let patterns = arms_patterns(type_name, &arms, mode);
 
-fn token_lex_arm(variant_name: &Ident, lit: &LitStr) -> proc_macro2::TokenStream {
-    let is_word_token = lit
-        .value()
-        .chars()
-        .all(|character| character.is_alphabetic() || character == '_');
-    if is_word_token {
+    let input_type = mode.input_type();
+    let arm_code = arms.iter().map(|arm| &arm.code);
+    let lex_one = quote! {
+        #[allow(unused_variables)]
+        fn try_lex_after(
+            previous: Option<#input_type>,
+            input: #input_type,
+        ) -> Result<Option<(Self, #input_type)>, ::rcket::InvalidToken> {
+            #(#arm_code)*
+            Ok(None)
+        }
+
+        fn lex_one(input: #input_type) -> Option<(Self, #input_type)> {
+            Self::try_lex_one(input).ok().flatten()
+        }
+    };
+
+    let partial_lens: Vec<&proc_macro2::TokenStream> = arms
+        .iter()
+        .filter_map(|arm| arm.partial_len.as_ref())
+        .collect();
+    let partial_match_len = if partial_lens.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn partial_match_len(input: #input_type) -> usize {
+                0usize #(.max(#partial_lens))*
+            }
+        }
+    };
+
+    let error_token = error_variant.map(|variant| {
+        let variant_name = &variant.ident;
+        let value = if single_unnamed_field(variant).is_some() {
+            quote! { Self::#variant_name(::std::convert::From::from(text)) }
+        } else {
+            quote! { Self::#variant_name }
+        };
         quote! {
-            if let Some(rest) = input.strip_prefix(#lit) {
-                if rest.is_empty() || !rest.starts_with(|character: char| character.is_alphanumeric() || character == '_') {
-                    return Some((Self::#variant_name, rest));
+            fn error_token(text: #input_type) -> Option<Self> {
+                Some(#value)
+            }
+        }
+    });
+
+    let methods = quote! {
+        #lex_one
+        #partial_match_len
+        #error_token
+        #patterns
+    };
+    (methods, wraps)
+}
+
+/// The `patterns` method, listing what `#[lex(check)]` checks in the order `arms` are tried.
+fn arms_patterns(type_name: &Ident, arms: &[LexArm], mode: &InputMode) -> proc_macro2::TokenStream {
+    let lex_trait = mode.lex_trait();
+    let pushes = arms.iter().map(|arm| {
+        let variant = format!("{}::{}", type_name, arm.variant_name);
+        let conditional = arm.conditional;
+        let text = match check::literal_text(&arm.matcher) {
+            Some(text) => quote! { Some(#text) },
+            None => quote! { None },
+        };
+        let matcher = match &arm.matcher {
+            ArmMatcher::Token(lit) => quote! { ::rcket::lex::check::PatternMatcher::Token(#lit) },
+            ArmMatcher::Regex { lit, word_boundary } => quote! {
+                ::rcket::lex::check::PatternMatcher::Regex {
+                    pattern: #lit,
+                    word_boundary: #word_boundary,
                 }
+            },
+            // a wrapped enum's patterns are conditional on the variant wrapping it
+            ArmMatcher::Wrapped(inner_type) => {
+                return quote! {
+                    patterns.extend(<#inner_type as #lex_trait>::patterns().into_iter().map(
+                        |pattern| ::rcket::lex::check::Pattern {
+                            conditional: pattern.conditional || #conditional,
+                            ..pattern
+                        },
+                    ));
+                };
             }
+            ArmMatcher::Opaque => return quote! {},
+        };
+        quote! {
+            patterns.push(::rcket::lex::check::Pattern {
+                variant: #variant,
+                matcher: #matcher,
+                text: #text,
+                conditional: #conditional,
+            });
+        }
+    });
+    quote! {
+        #[allow(unused_mut)]
+        fn patterns() -> Vec<::rcket::lex::check::Pattern> {
+            let mut patterns = Vec::new();
+            #(#pushes)*
+            patterns
+        }
+    }
+}
+
+/// Combines a variant's lookaround attributes into one condition over `rest`, the input after the
+/// match, and `previous`, the text of the token before it.
+fn variant_guard(variant: &Variant, mode: &InputMode) -> Option<proc_macro2::TokenStream> {
+    let conditions: Vec<proc_macro2::TokenStream> = variant
+        .attrs
+        .iter()
+        .filter_map(|attribute| {
+            let path = attribute.path();
+            let (followed, negated) = if path.is_ident("followed_by") {
+                (true, false)
+            } else if path.is_ident("not_followed_by") {
+                (true, true)
+            } else if path.is_ident("preceded_by") {
+                (false, false)
+            } else if path.is_ident("not_preceded_by") {
+                (false, true)
+            } else {
+                return None;
+            };
+            let LexCondition(pattern) = match attribute.parse_args::<LexCondition>() {
+                Ok(condition) => condition,
+                Err(error) => {
+                    emit_error!(error.span(), "{}", error);
+                    return None;
+                }
+            };
+            let lit = &pattern.lit;
+            let condition = match (&pattern.kind, followed) {
+                (LexPatternKind::Token, true) => {
+                    let literal = mode.literal(lit);
+                    quote! { rest.starts_with(#literal) }
+                }
+                (LexPatternKind::Regex, true) => {
+                    pattern_matches_empty(&pattern.kind, lit, mode);
+                    let anchored = LitStr::new(&format!("^(?:{})", lit.value()), lit.span());
+                    let regex = mode.cached_regex(&anchored);
+                    quote! { #regex.is_match(rest) }
+                }
+                (LexPatternKind::Token, false) => {
+                    let literal = mode.literal(lit);
+                    quote! { previous.is_some_and(|previous| previous == #literal) }
+                }
+                (LexPatternKind::Regex, false) => {
+                    pattern_matches_empty(&pattern.kind, lit, mode);
+                    let anchored = LitStr::new(&format!("^(?:{})$", lit.value()), lit.span());
+                    let regex = mode.cached_regex(&anchored);
+                    quote! { previous.is_some_and(|previous| #regex.is_match(previous)) }
+                }
+            };
+            Some(if negated {
+                quote! { !(#condition) }
+            } else {
+                condition
+            })
+        })
+        .collect();
+
+    (!conditions.is_empty()).then(|| quote! { (#(#conditions)&&*) })
+}
+
+/// Where-clause bounds for variant fields that use the enum's type parameters: values converted
+/// from matched text go through `FromStr` and keep its error message, and wrapped lexers have to
+/// lex the same input. Matcher values need whatever their `TryFrom` conversion does, which the
+/// enum has to declare itself.
+fn field_bounds(
+    data_enum: &DataEnum,
+    generics: &Generics,
+    mode: &InputMode,
+) -> Vec<WherePredicate> {
+    let lex_trait = mode.lex_trait();
+    let mut bounds: Vec<WherePredicate> = vec![];
+    for variant in &data_enum.variants {
+        let Some(field_type) = single_unnamed_field(variant) else {
+            continue;
+        };
+        if !mentions_type_parameter(field_type, generics)
+            || is_error_variant(variant)
+            || matches!(field_type, Type::Reference(_))
+        {
+            continue;
+        }
+        let has_attribute = |name: &str| {
+            variant
+                .attrs
+                .iter()
+                .any(|attribute| attribute.path().is_ident(name))
+        };
+        if has_attribute("matcher") || has_attribute("token") {
+            continue;
         }
+        if has_attribute("regex") || has_attribute("seq") || has_attribute("choice") {
+            bounds.push(parse_quote! { #field_type: ::std::str::FromStr });
+            bounds.push(parse_quote! {
+                <#field_type as ::std::str::FromStr>::Err: ::std::fmt::Display
+            });
+        } else {
+            bounds.push(parse_quote! { #field_type: #lex_trait });
+        }
+    }
+    bounds
+}
+
+pub(crate) fn is_error_variant(variant: &Variant) -> bool {
+    variant
+        .attrs
+        .iter()
+        .any(|attribute| attribute.path().is_ident("error"))
+}
+
+/// Checks a pattern at expansion time, reporting regex syntax errors on the literal's span.
+/// Returns whether the pattern can match the empty string.
+fn pattern_matches_empty(kind: &LexPatternKind, lit: &LitStr, mode: &InputMode) -> bool {
+    // byte patterns may match invalid UTF-8, e.g. `(?-u:\xFF)`
+    let mut parser = regex_syntax::ParserBuilder::new().utf8(!mode.bytes).build();
+    match kind {
+        LexPatternKind::Token => lit.value().is_empty(),
+        LexPatternKind::Regex => match parser.parse(&lit.value()) {
+            Ok(hir) => hir.properties().minimum_len() == Some(0),
+            Err(error) => {
+                emit_error!(lit.span(), "invalid regex pattern\n{}", error);
+                false
+            }
+        },
+    }
+}
+
+fn reject_empty_pattern(kind: &LexPatternKind, lit: &LitStr, mode: &InputMode) {
+    if pattern_matches_empty(kind, lit, mode) {
+        emit_error!(
+            lit.span(),
+            "pattern {:?} can match the empty string, which never produces a token",
+            lit.value()
+        );
+    }
+}
+
+/// Word tokens such as keywords only match when they aren't followed by more identifier characters.
+pub(crate) fn is_word_token(text: &str) -> bool {
+    text.chars()
+        .all(|character| character.is_alphabetic() || character == '_')
+}
+
+fn token_lex_arm(
+    variant_name: &Ident,
+    lit: &LitStr,
+    guard: &proc_macro2::TokenStream,
+    mode: &InputMode,
+) -> proc_macro2::TokenStream {
+    let literal = mode.literal(lit);
+    let at_word_boundary = if is_word_token(&lit.value()) {
+        mode.at_word_boundary(quote! { rest })
     } else {
-        quote! {
-            if let Some(rest) = input.strip_prefix(#lit) {
-                return Some((Self::#variant_name, rest));
+        quote! { true }
+    };
+    quote! {
+        if let Some(rest) = input.strip_prefix(#literal) {
+            if #at_word_boundary && #guard {
+                return Ok(Some((Self::#variant_name, rest)));
             }
         }
     }
 }
 
+/// Typed regex arms report values their `FromStr` rejects, like an `i32` that overflows, instead
+/// of letting another arm take the text.
 fn regex_lex_arm(
     variant_name: &Ident,
     lit: &LitStr,
     field_type: Option<&Type>,
+    guard: &proc_macro2::TokenStream,
+    mode: &InputMode,
 ) -> proc_macro2::TokenStream {
     let anchored = format!("^(?:{})", lit.value());
-    let anchored_lit = LitStr::new(&anchored, lit.span());
+    let regex = mode.cached_regex(&LitStr::new(&anchored, lit.span()));
     if let Some(field_type) = field_type {
+        let value = mode.field_value(field_type, quote! { &input[..match_result.end()] });
         quote! {
             {
-                let re = ::regex::Regex::new(#anchored_lit).unwrap();
+                let re = #regex;
                 if let Some(match_result) = re.find(input) {
-                    if match_result.end() > 0 {
-                        if let Ok(value) = input[..match_result.end()].parse::<#field_type>() {
-                            return Some((Self::#variant_name(value), &input[match_result.end()..]));
-                        }
+                    let rest = &input[match_result.end()..];
+                    if match_result.end() > 0 && #guard {
+                        return match #value {
+                            Ok(value) => Ok(Some((Self::#variant_name(value), rest))),
+                            Err(message) => Err(::rcket::InvalidToken { len: match_result.end(), message }),
+                        };
                     }
                 }
             }
         }
     } else {
+        let at_word_boundary = mode.at_word_boundary(quote! { rest });
         quote! {
             {
-                let re = ::regex::Regex::new(#anchored_lit).unwrap();
+                let re = #regex;
                 if let Some(match_result) = re.find(input) {
                     if match_result.end() > 0 {
                         let rest = &input[match_result.end()..];
-                        if rest.is_empty() || !rest.starts_with(|character: char| character.is_alphanumeric() || character == '_') {
-                            return Some((Self::#variant_name, rest));
+                        if #at_word_boundary && #guard {
+                            return Ok(Some((Self::#variant_name, rest)));
                         }
                     }
                 }
@@ -177,6 +804,8 @@ fn seq_lex_arm(
     variant_name: &Ident,
     patterns: &[LexPattern],
     field_type: Option<&Type>,
+    guard: &proc_macro2::TokenStream,
+    mode: &InputMode,
 ) -> proc_macro2::TokenStream {
     let mut steps: Vec<proc_macro2::TokenStream> = vec![];
     let mut capture_binding: Option<Ident> = None;
@@ -185,54 +814,202 @@ fn seq_lex_arm(
         let lit = &pattern.lit;
         match pattern.kind {
             LexPatternKind::Token => {
-                steps.push(quote! { let rest = rest.strip_prefix(#lit)?; });
+                let literal = mode.literal(lit);
+                steps.push(quote! {
+                    let Some(rest) = rest.strip_prefix(#literal) else { return Ok(None); };
+                });
             }
             LexPatternKind::Regex => {
                 let anchored = format!("^(?:{})", lit.value());
-                let anchored_lit = LitStr::new(&anchored, lit.span());
-                let binding = format_ident!("capture_{}", index);
-                capture_binding = Some(binding.clone());
-                if let Some(capture_type) = field_type {
-                    steps.push(quote! {
-                        let re = ::regex::Regex::new(#anchored_lit).unwrap();
-                        let match_result = re.find(rest)?;
-                        if match_result.start() != 0 { return None; }
-                        let #binding: #capture_type = rest[..match_result.end()].parse().ok()?;
-                        let rest = &rest[match_result.end()..];
-                    });
-                } else {
-                    steps.push(quote! {
-                        let re = ::regex::Regex::new(#anchored_lit).unwrap();
-                        let match_result = re.find(rest)?;
-                        if match_result.start() != 0 { return None; }
-                        let rest = &rest[match_result.end()..];
-                    });
-                }
+                let regex = mode.cached_regex(&LitStr::new(&anchored, lit.span()));
+                let capture = field_type.map(|_| {
+                    let binding = format_ident!("capture_{}", index);
+                    capture_binding = Some(binding.clone());
+                    quote! { let #binding = &rest[..match_result.end()]; }
+                });
+                steps.push(quote! {
+                    let re = #regex;
+                    let Some(match_result) = re.find(rest) else { return Ok(None); };
+                    #capture
+                    let rest = &rest[match_result.end()..];
+                });
             }
         }
     }
 
-    let return_value = if let Some(binding) = capture_binding {
-        quote! { Self::#variant_name(#binding) }
-    } else {
-        quote! { Self::#variant_name }
+    // the capture is converted once the whole sequence matched, so errors cover all of it
+    let return_value = match (capture_binding, field_type) {
+        (Some(binding), Some(capture_type)) => {
+            let value = mode.field_value(capture_type, quote! { #binding });
+            quote! {
+                match #value {
+                    Ok(value) => Self::#variant_name(value),
+                    Err(message) => {
+                        return Err(::rcket::InvalidToken { len: input.len() - rest.len(), message });
+                    }
+                }
+            }
+        }
+        _ => quote! { Self::#variant_name },
     };
 
+    let input_type = mode.input_type();
     quote! {
-        if let Some(result) = (|| -> Option<(Self, &str)> {
+        if let Some(result) = (|| -> Result<Option<(Self, #input_type)>, ::rcket::InvalidToken> {
             let rest = input;
             #(#steps)*
-            Some((#return_value, rest))
-        })() {
-            return Some(result);
+            if !#guard {
+                return Ok(None);
+            }
+            Ok(Some((#return_value, rest)))
+        })()? {
+            return Ok(Some(result));
         }
     }
 }
 
-fn bare_lex_arm(variant_name: &Ident, inner_type: &Type) -> proc_macro2::TokenStream {
+/// Runs the steps of a sequence until one fails and returns how much input they consumed, so an
+/// unterminated `"string` is reported as one error instead of lexing its contents.
+fn seq_partial_len(patterns: &[LexPattern], mode: &InputMode) -> proc_macro2::TokenStream {
+    let steps = patterns.iter().map(|pattern| {
+        let lit = &pattern.lit;
+        match pattern.kind {
+            LexPatternKind::Token => {
+                let literal = mode.literal(lit);
+                quote! {
+                    match rest.strip_prefix(#literal) {
+                        Some(next) => rest = next,
+                        None => return input.len() - rest.len(),
+                    }
+                }
+            }
+            LexPatternKind::Regex => {
+                let anchored = format!("^(?:{})", lit.value());
+                let regex = mode.cached_regex(&LitStr::new(&anchored, lit.span()));
+                quote! {
+                    let re = #regex;
+                    match re.find(rest) {
+                        Some(match_result) => rest = &rest[match_result.end()..],
+                        None => return input.len() - rest.len(),
+                    }
+                }
+            }
+        }
+    });
     quote! {
-        if let Some((value, rest)) = <#inner_type as ::rcket::Lex>::lex_one(input) {
-            return Some((Self::#variant_name(value), rest));
+        (|| -> usize {
+            let mut rest = input;
+            #(#steps)*
+            input.len() - rest.len()
+        })()
+    }
+}
+
+/// Calls a matcher function such as `rcket::lex::builtin::float`, converting its value into the
+/// field with `TryFrom` so a `u128` integer fits any integer field that can hold it. The function
+/// has to take the enum's input, so the text matchers of `rcket::lex::builtin` are an error on the
+/// path with `#[lex(bytes)]`.
+fn matcher_lex_arm(
+    variant_name: &Ident,
+    path: &Path,
+    field_type: Option<&Type>,
+    guard: &proc_macro2::TokenStream,
+    mode: &InputMode,
+) -> proc_macro2::TokenStream {
+    if mode.bytes && is_builtin_matcher(path) {
+        emit_error!(
+            path,
+            "the `rcket::lex::builtin` matchers lex text, they can't be used with `#[lex(bytes)]`"
+        );
+        return quote! {};
+    }
+    let input_type = mode.input_type();
+    let matcher = quote_spanned! {path.span()=>
+        {
+            let matcher: fn(#input_type) -> _ = #path;
+            matcher
         }
+    };
+    let return_value = match field_type {
+        Some(field_type) => quote! {
+            #[allow(clippy::useless_conversion)]
+            let converted = <#field_type as ::std::convert::TryFrom<_>>::try_from(value);
+            return match converted {
+                Ok(value) => Ok(Some((Self::#variant_name(value), rest))),
+                Err(error) => Err(::rcket::InvalidToken { len, message: error.to_string() }),
+            };
+        },
+        None => quote! {
+            return Ok(Some((Self::#variant_name, rest)));
+        },
+    };
+    quote! {
+        // an empty match would lex the same token forever, it's no match
+        if let Some((value, len)) = #matcher(input)? && len > 0 {
+            let rest = &input[len..];
+            if #guard {
+                #return_value
+            }
+        }
+    }
+}
+
+/// Whether `path` is spelled out as one of `rcket::lex::builtin`'s matchers. Others taking text,
+/// like imported ones, are caught by the type of the matcher instead.
+fn is_builtin_matcher(path: &Path) -> bool {
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+    segments.len() == 4 && segments[..3] == ["rcket", "lex", "builtin"]
+}
+
+fn bare_lex_arm(
+    variant_name: &Ident,
+    inner_type: &Type,
+    guard: &proc_macro2::TokenStream,
+    mode: &InputMode,
+) -> proc_macro2::TokenStream {
+    let lex_trait = mode.lex_trait();
+    quote! {
+        if let Some((value, rest)) = <#inner_type as #lex_trait>::try_lex_after(previous, input)? {
+            if #guard {
+                return Ok(Some((Self::#variant_name(value), rest)));
+            }
+        }
+    }
+}
+
+fn is_reference_to(field_type: &Type, name: &str) -> bool {
+    if let Type::Reference(reference) = field_type
+        && reference.mutability.is_none()
+        && let Type::Path(type_path) = reference.elem.as_ref()
+    {
+        return type_path.path.is_ident(name);
+    }
+    false
+}
+
+fn is_byte_slice_reference(field_type: &Type) -> bool {
+    if let Type::Reference(reference) = field_type
+        && reference.mutability.is_none()
+        && let Type::Slice(slice) = reference.elem.as_ref()
+        && let Type::Path(type_path) = slice.elem.as_ref()
+    {
+        return type_path.path.is_ident("u8");
+    }
+    false
+}
+
+fn is_byte_vec(field_type: &Type) -> bool {
+    if let Type::Path(type_path) = field_type
+        && let Some(segment) = type_path.path.segments.last()
+        && segment.ident == "Vec"
+        && let PathArguments::AngleBracketed(angle_arguments) = &segment.arguments
+        && let Some(GenericArgument::Type(Type::Path(element))) = angle_arguments.args.first()
+    {
+        return element.path.is_ident("u8");
     }
+    false
 }
@@ -2,16 +2,39 @@ extern crate proc_macro;
 use proc_macro::TokenStream;
 use proc_macro_error::proc_macro_error;
 
+mod check;
+mod kind;
 mod lex;
 mod node;
 
 #[proc_macro_error]
-#[proc_macro_derive(Node, attributes(token, extract, node, prec, infix))]
+#[proc_macro_derive(
+    Node,
+    attributes(
+        token, extract, node, prec, infix, sep, many, many1, delimited, cut, recover, error, peek,
+        not
+    )
+)]
 pub fn derive_node(input: TokenStream) -> TokenStream {
     node::derive_node(input)
 }
 
-#[proc_macro_derive(Lex, attributes(token, regex, seq, choice))]
+#[proc_macro_derive(
+    Lex,
+    attributes(
+        lex,
+        token,
+        regex,
+        seq,
+        choice,
+        matcher,
+        error,
+        followed_by,
+        not_followed_by,
+        preceded_by,
+        not_preceded_by
+    )
+)]
 #[proc_macro_error]
 pub fn derive_lex(input: TokenStream) -> TokenStream {
     lex::derive_lex(input)
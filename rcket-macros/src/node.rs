@@ -1,134 +1,1107 @@
 use proc_macro::TokenStream;
-use quote::{format_ident, quote};
+use proc_macro_error::{abort, emit_error};
+use quote::{ToTokens, format_ident, quote};
 use syn::{
-    Data, DataEnum, DataStruct, DeriveInput, Fields, GenericArgument, Ident, Index, Path,
-    PathArguments, Type, Variant, parse_macro_input,
+    Attribute, Data, DataEnum, DataStruct, DeriveInput, Fields, GenericArgument, GenericParam,
+    Generics, Ident, Index, Lifetime, LitInt, Path, PathArguments, Type, Variant, WherePredicate,
+    parse_macro_input, parse_quote,
 };
 
+use crate::lex::is_error_variant;
+
 pub(crate) fn derive_node(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let type_name = &input.ident;
+    if let Data::Union(_) = &input.data {
+        abort!(
+            type_name,
+            "`Node` can only be derived for structs and enums"
+        );
+    }
 
-    let token_type = input
-        .attrs
-        .iter()
-        .find(|attribute| attribute.path().is_ident("node"))
-        .and_then(|attribute| {
-            attribute
-                .parse_args_with(|stream: syn::parse::ParseStream| {
-                    let _key: Ident = stream.parse()?;
-                    let _equals: syn::Token![=] = stream.parse()?;
-                    let type_ident: Ident = stream.parse()?;
-                    Ok(type_ident)
-                })
-                .ok()
+    let options = NodeOptions::from_attributes(&input.attrs);
+    // nodes with lifetimes borrow from their tokens, so the default token type shares them
+    let token_type = options.token_type.unwrap_or_else(|| {
+        let lifetimes: Vec<&Lifetime> = input
+            .generics
+            .lifetimes()
+            .map(|lifetime_param| &lifetime_param.lifetime)
+            .collect();
+        if lifetimes.is_empty() {
+            parse_quote! { Token }
+        } else {
+            parse_quote! { Token<#(#lifetimes),*> }
+        }
+    });
+    let (node_bounds, display_bounds) =
+        field_bounds(&input.data, type_name, &input.generics, &token_type);
+    let mut node_generics = input.generics.clone();
+    node_generics
+        .make_where_clause()
+        .predicates
+        .extend(node_bounds);
+    let mut display_generics = input.generics.clone();
+    display_generics
+        .make_where_clause()
+        .predicates
+        .extend(display_bounds);
+    if options.memo {
+        let (_, type_generics, _) = input.generics.split_for_impl();
+        node_generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote! {
+                #type_name #type_generics: ::std::clone::Clone + 'static
+            });
+    }
+    let infix = match &input.data {
+        Data::Enum(data_enum) => data_enum.variants.iter().any(is_infix),
+        _ => false,
+    };
+    let rule = rule_id(type_name, &input.generics);
+    if infix || options.memo {
+        node_generics
+            .make_where_clause()
+            .predicates
+            .extend(static_bounds(&input.generics));
+    }
+    let (impl_generics, type_generics, where_clause) = node_generics.split_for_impl();
+
+    let (output_type, mut parse_body) = match &input.data {
+        Data::Struct(data_struct) => derive_struct(data_struct, &input.attrs, type_name),
+        Data::Enum(data_enum) => derive_enum(data_enum, &input.attrs, &token_type, &rule),
+        Data::Union(_) => unreachable!("unions are rejected above"),
+    };
+    if let Data::Enum(data_enum) = &input.data
+        && let Some(recover) = recover_parse(data_enum, &input.attrs, &parse_body)
+    {
+        parse_body = recover;
+    }
+    if options.memo {
+        parse_body = quote! {
+            ::rcket::parse::memoized(#rule, tokens, || -> Option<(Self::Output, &[Self::Token])> {
+                #parse_body
+            })
+        };
+    }
+    parse_body = quote! {
+        ::rcket::parse::enter(tokens, || -> Option<(Self::Output, &[Self::Token])> {
+            #parse_body
         })
-        .unwrap_or_else(|| Ident::new("Token", proc_macro2::Span::call_site()));
+    };
 
-    let (output_type, parse_body) = match &input.data {
-        Data::Struct(data_struct) => derive_struct(data_struct, type_name, &token_type),
-        Data::Enum(data_enum) => derive_enum(data_enum, &token_type),
-        _ => (quote! { Self }, quote! { todo!() }),
+    let table = match &input.data {
+        Data::Struct(data_struct) => {
+            let First(node) = sequence_first(
+                &data_struct.fields,
+                Delimiters::from_attributes(&input.attrs).as_ref(),
+            );
+            quote! { ::rcket::first::FirstTable::node(#node) }
+        }
+        Data::Enum(data_enum) => enum_table(data_enum, &input.attrs),
+        Data::Union(_) => unreachable!("unions are rejected above"),
+    };
+    // a static in a generic impl is shared by all of its types, which only agree on their
+    // tables when they differ in lifetimes alone
+    let cache = if input.generics.type_params().next().is_none()
+        && input.generics.const_params().next().is_none()
+    {
+        quote! {{
+            static TABLE: ::std::sync::OnceLock<
+                ::std::boxed::Box<dyn ::std::any::Any + ::std::marker::Send + ::std::marker::Sync>,
+            > = ::std::sync::OnceLock::new();
+            Some(&TABLE)
+        }}
+    } else {
+        quote! { None }
+    };
+    let from_impls = match &input.data {
+        Data::Enum(data_enum) => from_impls(data_enum, type_name, &input.generics),
+        _ => quote! {},
     };
 
     let display_impl = match &input.data {
-        Data::Struct(data_struct) => display_impl_struct(data_struct, type_name),
-        Data::Enum(data_enum) => display_impl_enum(data_enum, type_name),
+        Data::Struct(data_struct) => display_impl_struct(data_struct, type_name, &display_generics),
+        Data::Enum(data_enum) => display_impl_enum(data_enum, type_name, &display_generics),
         _ => quote! {},
     };
 
     quote! {
-        impl ::rcket::Node for #type_name {
+        impl #impl_generics ::rcket::Node for #type_name #type_generics #where_clause {
             type Token = #token_type;
             type Output = #output_type;
-            fn parse_one(tokens: &[#token_type]) -> Option<(Self::Output, &[#token_type])> {
+            const INFIX: bool = #infix;
+            fn starts_with(token: &Self::Token) -> bool {
+                ::rcket::first::table_of::<Self>().node.admits(::rcket::HasKind::kind(token))
+            }
+            fn first() -> Option<Vec<String>> {
+                ::rcket::first::table_of::<Self>().node.names()
+            }
+            fn first_kinds() -> Option<Vec<<Self::Token as ::rcket::HasKind>::Kind>> {
+                ::rcket::first::table_of::<Self>().node.kinds()
+            }
+            #[allow(unused_variables)]
+            fn first_table(
+                visiting: &mut ::rcket::first::Visiting,
+            ) -> ::std::sync::Arc<::rcket::first::FirstTable<<Self::Token as ::rcket::HasKind>::Kind>> {
+                ::rcket::first::table::<Self, _>(#cache, visiting, |visiting| #table)
+            }
+            fn parse_one(tokens: &[Self::Token]) -> Option<(Self::Output, &[Self::Token])> {
                 #parse_body
             }
         }
+        #from_impls
         #display_impl
     }
     .into()
 }
 
+/// An expression for the `TypeId` that tells the node apart in `rcket::parse`, which is its own
+/// with every lifetime `'static`, since a `TypeId` can't have others. Its type parameters need
+/// `static_bounds` for it.
+fn rule_id(type_name: &Ident, generics: &Generics) -> proc_macro2::TokenStream {
+    let arguments = generics.params.iter().map(|param| match param {
+        GenericParam::Lifetime(_) => quote! { 'static },
+        GenericParam::Type(type_param) => type_param.ident.to_token_stream(),
+        GenericParam::Const(const_param) => const_param.ident.to_token_stream(),
+    });
+    quote! { ::std::any::TypeId::of::<#type_name<#(#arguments),*>>() }
+}
+
+fn static_bounds(generics: &Generics) -> Vec<WherePredicate> {
+    generics
+        .type_params()
+        .map(|type_param| {
+            let name = &type_param.ident;
+            parse_quote! { #name: 'static }
+        })
+        .collect()
+}
+
+/// Wraps an enum's `parse_body` in `rcket::parse::recover` when it's `#[recover(...)]`, with the
+/// sync tokens parsing resumes `after(...)` or `before(...)` and its `#[error]` variant for what's
+/// skipped.
+fn recover_parse(
+    data_enum: &DataEnum,
+    attributes: &[Attribute],
+    parse_body: &proc_macro2::TokenStream,
+) -> Option<proc_macro2::TokenStream> {
+    let error_variants: Vec<&Variant> = data_enum
+        .variants
+        .iter()
+        .filter(|variant| is_error_variant(variant))
+        .collect();
+    let Some(attribute) = attributes
+        .iter()
+        .find(|attribute| attribute.path().is_ident("recover"))
+    else {
+        if let Some(variant) = error_variants.first() {
+            emit_error!(
+                variant,
+                "`#[error]` variants are for enums marked `#[recover(...)]`"
+            );
+        }
+        return None;
+    };
+
+    let mut resumes = vec![];
+    let result = attribute.parse_nested_meta(|meta| {
+        let resume = if meta.path.is_ident("after") {
+            quote! { ::rcket::parse::Resume::After }
+        } else if meta.path.is_ident("before") {
+            quote! { ::rcket::parse::Resume::Before }
+        } else {
+            return Err(meta.error("expected `after(...)` or `before(...)`"));
+        };
+        meta.parse_nested_meta(|token| {
+            let check = token_check(&token.path);
+            resumes.push(quote! {
+                if #check {
+                    return Some(#resume);
+                }
+            });
+            Ok(())
+        })
+    });
+    if let Err(error) = result {
+        emit_error!(error.span(), "{}", error);
+    }
+
+    let [error_variant] = error_variants[..] else {
+        emit_error!(
+            attribute,
+            "`#[recover]` enums need one `#[error]` variant for the tokens they skip"
+        );
+        return None;
+    };
+    if single_unnamed_field(error_variant)
+        .and_then(unwrap_vec)
+        .is_none()
+    {
+        emit_error!(
+            error_variant,
+            "`#[error]` variants hold the skipped tokens, like `Error(Vec<Token>)`"
+        );
+    }
+    let error_name = &error_variant.ident;
+    Some(quote! {
+        ::rcket::parse::recover(
+            tokens,
+            |token: &Self::Token| -> Option<::rcket::parse::Resume> {
+                #(#resumes)*
+                None
+            },
+            Self::#error_name,
+            || -> Option<(Self, &[Self::Token])> { #parse_body },
+        )
+    })
+}
+
+/// The options in a node's `#[node(...)]` attribute, e.g. `#[node(token = Token<'a>, memo)]`.
+#[derive(Default)]
+struct NodeOptions {
+    /// The tokens the node is parsed from, `Token` when not given.
+    token_type: Option<Type>,
+    /// Whether parses are kept in the memo table of `rcket::parse::memoize`.
+    memo: bool,
+}
+
+impl NodeOptions {
+    fn from_attributes(attributes: &[Attribute]) -> Self {
+        let mut options = Self::default();
+        for attribute in attributes
+            .iter()
+            .filter(|attribute| attribute.path().is_ident("node"))
+        {
+            let result = attribute.parse_args_with(|stream: syn::parse::ParseStream| {
+                while !stream.is_empty() {
+                    let key: Ident = stream.parse()?;
+                    if key == "token" {
+                        let _equals: syn::Token![=] = stream.parse()?;
+                        options.token_type = Some(stream.parse()?);
+                    } else if key == "memo" {
+                        options.memo = true;
+                    } else {
+                        return Err(syn::Error::new(
+                            key.span(),
+                            "expected `token = Type` or `memo`",
+                        ));
+                    }
+                    if !stream.is_empty() {
+                        let _comma: syn::Token![,] = stream.parse()?;
+                    }
+                }
+                Ok(())
+            });
+            if let Err(error) = result {
+                emit_error!(error.span(), "{}", error);
+            }
+        }
+        options
+    }
+}
+
+/// Where-clause bounds for the fields that use the node's type parameters: parsed fields have to
+/// be nodes over the same tokens, extracted values `Clone` and the operands of `#[infix]` variants
+/// `From` the enum. The bounds the `Display` impl needs on displayed fields are returned second.
+fn field_bounds(
+    data: &Data,
+    type_name: &Ident,
+    generics: &Generics,
+    token_type: &Type,
+) -> (Vec<WherePredicate>, Vec<WherePredicate>) {
+    let (_, type_generics, _) = generics.split_for_impl();
+    let mut node_bounds: Vec<WherePredicate> = vec![];
+    let mut display_bounds: Vec<WherePredicate> = vec![];
+    let fields: Vec<(&syn::Field, bool)> = match data {
+        Data::Struct(data_struct) => data_struct
+            .fields
+            .iter()
+            .map(|field| (field, false))
+            .collect(),
+        Data::Enum(data_enum) => data_enum
+            .variants
+            .iter()
+            .filter(|variant| !is_error_variant(variant))
+            .flat_map(|variant| {
+                let extracted = variant
+                    .attrs
+                    .iter()
+                    .any(|attribute| attribute.path().is_ident("extract"));
+                variant.fields.iter().map(move |field| (field, extracted))
+            })
+            .collect(),
+        Data::Union(_) => vec![],
+    };
+
+    for (field, extracted) in fields {
+        let value_type = parsed_type(&field.ty);
+        if !mentions_type_parameter(value_type, generics)
+            || has_attribute(field, "token")
+            || is_phantom_data(value_type)
+        {
+            continue;
+        }
+        if extracted || has_attribute(field, "extract") {
+            node_bounds.push(parse_quote! { #value_type: ::std::clone::Clone });
+        } else {
+            node_bounds.push(parse_quote! {
+                #value_type: ::rcket::Node<Token = #token_type, Output = #value_type>
+            });
+        }
+        display_bounds.push(parse_quote! { #value_type: ::std::fmt::Display });
+    }
+    if let Data::Enum(data_enum) = data
+        && let Some(variant) = data_enum.variants.iter().find(|variant| is_infix(variant))
+        && let Some(field) = variant.fields.iter().next()
+        && mentions_type_parameter(&field.ty, generics)
+    {
+        let operand_type = parsed_type(&field.ty);
+        node_bounds.push(parse_quote! {
+            #operand_type: ::std::convert::From<#type_name #type_generics>
+        });
+    }
+
+    let dedup = |bounds: Vec<WherePredicate>| {
+        let mut seen = vec![];
+        bounds
+            .into_iter()
+            .filter(|bound| {
+                let text = quote! { #bound }.to_string();
+                let new = !seen.contains(&text);
+                seen.push(text);
+                new
+            })
+            .collect()
+    };
+    (dedup(node_bounds), dedup(display_bounds))
+}
+
+/// The type a field's values are parsed as, inside its `Option`, `Vec` and `Box`.
+fn parsed_type(field_type: &Type) -> &Type {
+    let field_type = unwrap_option(field_type).unwrap_or(field_type);
+    let field_type = unwrap_vec(field_type).unwrap_or(field_type);
+    unwrap_box(field_type).unwrap_or(field_type)
+}
+
+/// Whether one of the type's identifiers is a type parameter of `generics`.
+pub(crate) fn mentions_type_parameter(ty: &Type, generics: &Generics) -> bool {
+    fn mentions(tokens: proc_macro2::TokenStream, parameters: &[&Ident]) -> bool {
+        tokens.into_iter().any(|token| match token {
+            proc_macro2::TokenTree::Ident(ident) => parameters.contains(&&ident),
+            proc_macro2::TokenTree::Group(group) => mentions(group.stream(), parameters),
+            _ => false,
+        })
+    }
+    let parameters: Vec<&Ident> = generics
+        .type_params()
+        .map(|parameter| &parameter.ident)
+        .collect();
+    mentions(quote! { #ty }, &parameters)
+}
+
+fn is_phantom_data(ty: &Type) -> bool {
+    matches!(ty, Type::Path(type_path) if type_path.path.segments.last().is_some_and(|segment| segment.ident == "PhantomData"))
+}
+
+/// What a node, variant or field can start with, an expression of its `rcket::first::First` set.
+/// Nodes are looked up with the ones whose tables are being computed in `visiting`.
+struct First(proc_macro2::TokenStream);
+
+impl First {
+    /// Any token, for what can be empty.
+    fn any() -> Self {
+        First(quote! { ::rcket::first::First::any() })
+    }
+
+    fn empty() -> Self {
+        First(quote! { ::rcket::first::First::empty() })
+    }
+
+    /// The kind of the token variant at `path`, picked by name from the `{Enum}Kinds` of the enum
+    /// it's a variant of. The enum is inferred from a pattern matching the variant and the tokens
+    /// it's projected from, like in `projected_first_token`, since the path alone can't name a
+    /// generic one.
+    fn token(path: &Path) -> Self {
+        let Some(variant) = path.segments.last().map(|segment| &segment.ident) else {
+            return First::any();
+        };
+        First(quote! {
+            ::rcket::first::token::<Self::Token, _>(
+                |token| matches!(token, #path { .. }),
+                |kinds| kinds.#variant,
+            )
+        })
+    }
+
+    fn node(node_type: &Type) -> Self {
+        First(quote! {
+            <#node_type as ::rcket::Node>::first_table(visiting).node.clone()
+        })
+    }
+
+    fn union(firsts: Vec<First>) -> Self {
+        if firsts.is_empty() {
+            return First::empty();
+        }
+        let sets = firsts.iter().map(|First(set)| set);
+        First(quote! { ::rcket::first::First::union([#(#sets),*]) })
+    }
+
+    /// An `Option<Vec<String>>` expression of the names of the kinds, for errors.
+    fn names(&self) -> proc_macro2::TokenStream {
+        let First(set) = self;
+        quote! {{
+            #[allow(unused_variables)]
+            let visiting = &mut ::rcket::first::Visiting::default();
+            #set.names()
+        }}
+    }
+}
+
+/// A `bool` expression of whether `token` is the token variant at `path`.
+fn token_check(path: &Path) -> proc_macro2::TokenStream {
+    quote! { matches!(::rcket::Project::<_>::project(token), Some(#path { .. })) }
+}
+
+/// What a sequence of `fields` can start with: its opening delimiter, or its first field and
+/// anything after it while the fields before can be empty.
+fn sequence_first(fields: &Fields, delimiters: Option<&Delimiters>) -> First {
+    if let Some(delimiters) = delimiters {
+        return First::token(&delimiters.open);
+    }
+    let mut firsts = vec![];
+    for field in fields {
+        if let Some(delimiters) = Delimiters::from_attributes(&field.attrs) {
+            firsts.push(First::token(&delimiters.open));
+            return First::union(firsts);
+        }
+        let (first, optional) = if has_attribute(field, "peek") || has_attribute(field, "not") {
+            (None, true)
+        } else if let Some(element_type) = unwrap_vec(&field.ty) {
+            let min = Repetition::from_attributes(&field.attrs).min;
+            (field_first(field, element_type), min == 0)
+        } else if is_bool_type(&field.ty) && has_attribute(field, "token") {
+            (field_first(field, &field.ty), true)
+        } else if let Some(optional_type) = unwrap_option(&field.ty) {
+            (field_first(field, optional_type), true)
+        } else {
+            (field_first(field, &field.ty), is_phantom_data(&field.ty))
+        };
+        firsts.extend(first);
+        if !optional {
+            return First::union(firsts);
+        }
+    }
+    First::any()
+}
+
+/// What one `field_type` value of `field` starts with, nothing for `PhantomData`.
+fn field_first(field: &syn::Field, field_type: &Type) -> Option<First> {
+    let token_path = field
+        .attrs
+        .iter()
+        .find(|attribute| {
+            attribute.path().is_ident("token") || attribute.path().is_ident("extract")
+        })
+        .and_then(|attribute| attribute.parse_args::<Path>().ok());
+    if let Some(path) = token_path {
+        Some(First::token(&path))
+    } else if is_phantom_data(field_type) {
+        None
+    } else {
+        Some(First::node(unwrap_box(field_type).unwrap_or(field_type)))
+    }
+}
+
+/// The table of an enum: what its variants can start with, and its opening delimiter if it's
+/// `#[delimited]`, and each variant's set for dispatching on. Operations start with their
+/// operand, and aren't dispatched on like the `#[error]` variant.
+fn enum_table(data_enum: &DataEnum, attributes: &[Attribute]) -> proc_macro2::TokenStream {
+    let mut firsts: Vec<First> = data_enum
+        .variants
+        .iter()
+        .filter(|variant| !is_infix(variant) && !is_error_variant(variant))
+        .map(variant_first)
+        .collect();
+    if let Some(operand_type) = data_enum
+        .variants
+        .iter()
+        .find(|variant| is_infix(variant))
+        .and_then(|variant| variant.fields.iter().next())
+    {
+        firsts.push(First::node(
+            unwrap_box(&operand_type.ty).unwrap_or(&operand_type.ty),
+        ));
+    }
+    if let Some(delimiters) = Delimiters::from_attributes(attributes) {
+        firsts.push(First::token(&delimiters.open));
+    }
+    let First(node) = First::union(firsts);
+    let variants = data_enum.variants.iter().map(|variant| {
+        let First(set) = if is_infix(variant) || is_error_variant(variant) {
+            First::empty()
+        } else {
+            variant_first(variant)
+        };
+        set
+    });
+    quote! {
+        ::rcket::first::FirstTable {
+            node: #node,
+            variants: vec![#(#variants),*],
+        }
+    }
+}
+
+fn variant_first(variant: &Variant) -> First {
+    let token_paths: Vec<Path> = variant
+        .attrs
+        .iter()
+        .filter(|attribute| {
+            attribute.path().is_ident("token") || attribute.path().is_ident("extract")
+        })
+        .filter_map(|attribute| attribute.parse_args::<Path>().ok())
+        .collect();
+    if !token_paths.is_empty() {
+        return First::union(token_paths.iter().map(First::token).collect());
+    }
+    sequence_first(
+        &variant.fields,
+        Delimiters::from_attributes(&variant.attrs).as_ref(),
+    )
+}
+
 fn derive_struct(
     data_struct: &DataStruct,
+    attributes: &[Attribute],
     type_name: &Ident,
-    token_type: &Ident,
 ) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
-    let fields: Vec<&syn::Field> = match &data_struct.fields {
-        Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
-        Fields::Named(fields) => fields.named.iter().collect(),
-        Fields::Unit => vec![],
-    };
+    let parse_body = parse_sequence(
+        &data_struct.fields,
+        quote! { #type_name },
+        Delimiters::from_attributes(attributes),
+    );
 
+    (quote! { Self }, parse_body)
+}
+
+/// An expression parsing `fields` one after another and building `constructor` from them, giving
+/// `None` as soon as a field doesn't parse. Named fields are built by name, token fields become
+/// `()`. Once the field marked `#[cut]` is passed, a failure also fails the nearest choice around
+/// the sequence.
+fn parse_sequence(
+    fields: &Fields,
+    constructor: proc_macro2::TokenStream,
+    delimiters: Option<Delimiters>,
+) -> proc_macro2::TokenStream {
     let mut parse_steps = vec![];
     let mut field_bindings: Vec<Ident> = vec![];
+    let cut_fields = fields
+        .iter()
+        .filter(|field| has_attribute(field, "cut"))
+        .count();
+    if cut_fields > 1 {
+        emit_error!(fields, "only one field can be marked `#[cut]`");
+    }
 
     for (field_index, field) in fields.iter().enumerate() {
         let binding = format_ident!("field_{}", field_index);
         field_bindings.push(binding.clone());
 
-        let token_attribute = field
-            .attrs
-            .iter()
-            .find(|attribute| attribute.path().is_ident("token"));
-        let extract_attribute = field
-            .attrs
-            .iter()
-            .find(|attribute| attribute.path().is_ident("extract"));
-
-        if let Some(token_attribute) = token_attribute {
-            let path = token_attribute.parse_args::<Path>().unwrap();
-            let first_segment_ident = &path.segments[0].ident;
-            let token_pattern = quote! { #token_type::#first_segment_ident(#path) };
-            parse_steps.push(quote! {
-                let tokens = if let Some((#token_pattern, rest)) = tokens.split_first() { rest } else { return None; };
-                let #binding = ();
-            });
-        } else if let Some(extract_attribute) = extract_attribute {
-            let path = extract_attribute.parse_args::<Path>().unwrap();
-            parse_steps.push(quote! {
-                let (#binding, tokens) = if let Some((#token_type::Literal(#path(value)), rest)) = tokens.split_first() {
-                    (value.clone(), rest)
-                } else { return None; };
-            });
-        } else if let Some(inner_type) = unwrap_box(&field.ty) {
-            parse_steps.push(quote! {
-                let (#binding, tokens) = <#inner_type as ::rcket::Node>::parse_one(tokens)?;
-                let #binding = ::std::boxed::Box::new(#binding);
-            });
+        let optional_type = unwrap_option(&field.ty);
+        let step = if let Some(step) = predicate_step(field, &binding) {
+            step
+        } else if let Some(element_type) = unwrap_vec(&field.ty) {
+            let repetition = parse_repetition(field, element_type);
+            quote! {
+                let (#binding, tokens) = #repetition;
+            }
+        } else if is_bool_type(&field.ty) && has_attribute(field, "token") {
+            let parse = parse_field(field, &field.ty);
+            quote! {
+                let (#binding, tokens) = match #parse {
+                    Some(((), rest)) => (true, rest),
+                    None if ::rcket::parse::take_cut() => return None,
+                    None => (false, tokens),
+                };
+            }
+        } else if let Some(optional_type) = optional_type {
+            let parse = parse_field(field, optional_type);
+            quote! {
+                let (#binding, tokens) = match #parse {
+                    Some((value, rest)) => (Some(value), rest),
+                    None if ::rcket::parse::take_cut() => return None,
+                    None => (None, tokens),
+                };
+            }
         } else {
-            let field_type = &field.ty;
-            parse_steps.push(quote! {
-                let (#binding, tokens) = <#field_type as ::rcket::Node>::parse_one(tokens)?;
-            });
+            let parse = parse_field(field, &field.ty);
+            quote! {
+                let (#binding, tokens) = #parse?;
+            }
+        };
+        parse_steps.push(match Delimiters::from_attributes(&field.attrs) {
+            Some(delimiters) => delimiters.around(step, &binding),
+            None => step,
+        });
+        if has_attribute(field, "cut") {
+            parse_steps.push(quote! { cut.pass(tokens.len()); });
         }
     }
 
-    let parse_body = quote! {
-        (|| -> Option<_> {
-            #(#parse_steps)*
-            Some((#type_name(#(#field_bindings),*), tokens))
-        })()
+    let value = match fields {
+        Fields::Named(named) => {
+            let field_names = named.named.iter().map(|field| &field.ident);
+            quote! { #constructor { #(#field_names: #field_bindings),* } }
+        }
+        Fields::Unnamed(_) => quote! { #constructor(#(#field_bindings),*) },
+        Fields::Unit => quote! { #constructor },
+    };
+    let steps = quote! { #(#parse_steps)* };
+    let steps = match delimiters {
+        Some(delimiters) => delimiters.around(steps, &format_ident!("sequence")),
+        None => steps,
     };
+    let sequence = quote! {
+        ::rcket::parse::attempt(|| -> Option<_> {
+            #steps
+            Some((#value, tokens))
+        })
+    };
+    if cut_fields == 0 {
+        return sequence;
+    }
+    quote! {{
+        let cut = ::rcket::parse::Cut::default();
+        let result = #sequence;
+        cut.finish(result)
+    }}
+}
 
-    (quote! { Self }, parse_body)
+/// What a `#[peek(...)]` or `#[not(...)]` predicate checks the next tokens for.
+enum Lookahead {
+    /// A token variant, like `Symbol::LeftParen`.
+    Token(Path),
+    /// A node parsing from there, like `Call`.
+    Node(Type),
+}
+
+impl Lookahead {
+    /// `token = Symbol::LeftParen` for a token variant, `node = Call` for a node type.
+    fn parse(attribute: &Attribute) -> syn::Result<Self> {
+        attribute.parse_args_with(|stream: syn::parse::ParseStream| {
+            let expected = "expected `token = Path` or `node = Type`";
+            if !stream.peek2(syn::Token![=]) {
+                return Err(stream.error(expected));
+            }
+            let key: Ident = stream.parse()?;
+            let _equals: syn::Token![=] = stream.parse()?;
+            if key == "token" {
+                Ok(Lookahead::Token(stream.parse()?))
+            } else if key == "node" {
+                Ok(Lookahead::Node(stream.parse()?))
+            } else {
+                Err(syn::Error::new(key.span(), expected))
+            }
+        })
+    }
+
+    /// A `bool` expression of whether `tokens` start with it, consuming nothing.
+    fn matches(&self) -> proc_macro2::TokenStream {
+        match self {
+            Lookahead::Token(path) => {
+                let check = token_check(path);
+                quote! { tokens.first().is_some_and(|token| #check) }
+            }
+            Lookahead::Node(node_type) => quote! {
+                ::rcket::parse::lookahead(|| <#node_type as ::rcket::Node>::parse_one(tokens))
+            },
+        }
+    }
+
+    fn first(&self) -> First {
+        match self {
+            Lookahead::Token(path) => First::token(path),
+            Lookahead::Node(node_type) => First::node(node_type),
+        }
+    }
+}
+
+/// The step of a `#[peek(...)]` field, failing unless the next tokens match, or a `#[not(...)]`
+/// field, failing if they do. Both give `()` and consume nothing.
+fn predicate_step(field: &syn::Field, binding: &Ident) -> Option<proc_macro2::TokenStream> {
+    let attribute = field
+        .attrs
+        .iter()
+        .find(|attribute| attribute.path().is_ident("peek") || attribute.path().is_ident("not"))?;
+    let lookahead = match Lookahead::parse(attribute) {
+        Ok(lookahead) => lookahead,
+        Err(error) => {
+            emit_error!(
+                error.span(),
+                "{}, like `#[peek(token = Symbol::LeftParen)]` or `#[peek(node = Call)]`",
+                error
+            );
+            return Some(quote! {});
+        }
+    };
+    if !is_unit_type(&field.ty) {
+        emit_error!(
+            field.ty,
+            "`#[peek]` and `#[not]` fields consume nothing, so they're `()`"
+        );
+    }
+    let matches = lookahead.matches();
+    let step = if attribute.path().is_ident("peek") {
+        let names = lookahead.first().names();
+        quote! {
+            if !#matches {
+                ::rcket::parse::expected(tokens.len(), || #names);
+                return None;
+            }
+        }
+    } else {
+        quote! {
+            if #matches {
+                return None;
+            }
+        }
+    };
+    Some(quote! {
+        #step
+        let #binding = ();
+    })
+}
+
+/// `#[delimited(Open, Close)]` tokens around a field, the fields of a struct or variant, or a whole
+/// enum.
+struct Delimiters {
+    open: Path,
+    close: Path,
+}
+
+impl Delimiters {
+    fn from_attributes(attributes: &[Attribute]) -> Option<Self> {
+        let attribute = attributes
+            .iter()
+            .find(|attribute| attribute.path().is_ident("delimited"))?;
+        let result = attribute.parse_args_with(|stream: syn::parse::ParseStream| {
+            let open = stream.parse()?;
+            stream.parse::<syn::Token![,]>()?;
+            let close = stream.parse()?;
+            Ok(Delimiters { open, close })
+        });
+        result
+            .map_err(|error| {
+                emit_error!(
+                    error.span(),
+                    "expected two token paths, like `#[delimited(Symbol::LeftParen, Symbol::RightParen)]`"
+                )
+            })
+            .ok()
+    }
+
+    /// Wraps parsing `steps` in the delimiters, reporting an unclosed delimiter when the steps
+    /// parse but the closing token isn't next. `name` keeps nested delimiters apart.
+    fn around(&self, steps: proc_macro2::TokenStream, name: &Ident) -> proc_macro2::TokenStream {
+        let Delimiters { open, close } = self;
+        let open_remaining = format_ident!("{}_open_remaining", name);
+        let open_token = projected_first_token(open);
+        let close_token = projected_first_token(close);
+        let expected_open = expected_token(open);
+        quote! {
+            let #open_remaining = tokens.len();
+            let tokens = match #open_token {
+                Some((#open, rest)) => rest,
+                _ => {
+                    #expected_open
+                    return None;
+                }
+            };
+            #steps
+            let tokens = match #close_token {
+                Some((#close, rest)) => rest,
+                _ => {
+                    ::rcket::parse::unclosed(#open_remaining, tokens.len());
+                    return None;
+                }
+            };
+        }
+    }
+}
+
+/// `#[sep(...)]` and `#[many(...)]` options of a `Vec` field.
+#[derive(Default)]
+struct Repetition {
+    /// A token between elements, optionally allowed after the last one as well.
+    separator: Option<Path>,
+    trailing: bool,
+    min: usize,
+    max: Option<usize>,
+}
+
+impl Repetition {
+    fn from_attributes(attributes: &[Attribute]) -> Self {
+        let mut repetition = Repetition::default();
+        for attribute in attributes {
+            let result = if attribute.path().is_ident("sep") {
+                attribute.parse_args_with(|stream: syn::parse::ParseStream| {
+                    repetition.separator = Some(stream.parse()?);
+                    if stream.parse::<Option<syn::Token![,]>>()?.is_some() {
+                        let option: Ident = stream.parse()?;
+                        if option != "trailing" {
+                            return Err(syn::Error::new(option.span(), "expected `trailing`"));
+                        }
+                        repetition.trailing = true;
+                    }
+                    Ok(())
+                })
+            } else if attribute.path().is_ident("many1") {
+                repetition.min = 1;
+                Ok(())
+            } else if attribute.path().is_ident("many") {
+                attribute.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("min") {
+                        repetition.min = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+                        Ok(())
+                    } else if meta.path.is_ident("max") {
+                        repetition.max = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                        Ok(())
+                    } else {
+                        Err(meta.error(
+                            "unknown repetition option, expected `min = ...` or `max = ...`",
+                        ))
+                    }
+                })
+            } else {
+                Ok(())
+            };
+            if let Err(error) = result {
+                emit_error!(error.span(), "{}", error);
+            }
+        }
+        repetition
+    }
+}
+
+/// An expression parsing a `Vec` field's elements, giving `(elements, rest)` or returning `None`
+/// from the sequence when there are fewer than the minimum. Stops at the maximum, at an element
+/// that doesn't parse, or at one that consumes nothing, so it can't loop forever.
+fn parse_repetition(field: &syn::Field, element_type: &Type) -> proc_macro2::TokenStream {
+    let Repetition {
+        separator,
+        trailing,
+        min,
+        max,
+    } = Repetition::from_attributes(&field.attrs);
+    let parse_element = parse_field(field, element_type);
+    let max = match max {
+        Some(max) => quote! { Some(#max) },
+        None => quote! { None::<usize> },
+    };
+    // a separator after the last element is only consumed if it's allowed there
+    let keep_trailing = if trailing {
+        quote! { rest = tokens; }
+    } else {
+        quote! {}
+    };
+    let skip_separator = match &separator {
+        Some(separator) => {
+            let first_token = projected_first_token(separator);
+            let expected = expected_token(separator);
+            quote! {
+                if !elements.is_empty() {
+                    match #first_token {
+                        Some((#separator, rest)) => tokens = rest,
+                        _ => { #expected break }
+                    }
+                }
+            }
+        }
+        None => quote! {},
+    };
+
+    quote! {{
+        let mut elements = ::std::vec::Vec::new();
+        let mut rest = tokens;
+        loop {
+            if #max.is_some_and(|max| elements.len() >= max) {
+                break;
+            }
+            #[allow(unused_mut)]
+            let mut tokens = rest;
+            #skip_separator
+            let Some((element, after)) = (#parse_element) else {
+                if ::rcket::parse::take_cut() {
+                    return None;
+                }
+                #keep_trailing
+                break;
+            };
+            let progressed = after.len() < rest.len();
+            elements.push(element);
+            rest = after;
+            if !progressed {
+                break;
+            }
+        }
+        if elements.len() < #min {
+            return None;
+        }
+        (elements, rest)
+    }}
+}
+
+/// An expression parsing one `field_type` value from `tokens`, giving `Option<(value, rest)>`.
+/// Token fields give `()`.
+fn parse_field(field: &syn::Field, field_type: &Type) -> proc_macro2::TokenStream {
+    let token_attribute = field
+        .attrs
+        .iter()
+        .find(|attribute| attribute.path().is_ident("token"));
+    let extract_attribute = field
+        .attrs
+        .iter()
+        .find(|attribute| attribute.path().is_ident("extract"));
+
+    if let Some(token_attribute) = token_attribute {
+        let path = token_attribute.parse_args::<Path>().unwrap();
+        let first_token = projected_first_token(&path);
+        let expected = expected_token(&path);
+        quote! {
+            match #first_token {
+                Some((#path, rest)) => Some(((), rest)),
+                _ => { #expected None }
+            }
+        }
+    } else if let Some(extract_attribute) = extract_attribute {
+        let path = extract_attribute.parse_args::<Path>().unwrap();
+        let first_token = projected_first_token(&path);
+        let expected = expected_token(&path);
+        quote! {
+            match #first_token {
+                Some((#path(value), rest)) => Some((value.clone(), rest)),
+                _ => { #expected None }
+            }
+        }
+    } else if is_phantom_data(field_type) {
+        quote! { Some((::std::marker::PhantomData, tokens)) }
+    } else if let Some(inner_type) = unwrap_box(field_type) {
+        quote! {
+            <#inner_type as ::rcket::Node>::parse_one(tokens)
+                .map(|(value, rest)| (::std::boxed::Box::new(value), rest))
+        }
+    } else {
+        quote! { <#field_type as ::rcket::Node>::parse_one(tokens) }
+    }
+}
+
+/// Records that the token at `path` was expected next, for when it isn't.
+fn expected_token(path: &Path) -> proc_macro2::TokenStream {
+    let names = First::token(path).names();
+    quote! { ::rcket::parse::expected(tokens.len(), || #names); }
+}
+
+fn has_attribute(field: &syn::Field, name: &str) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attribute| attribute.path().is_ident(name))
 }
 
 fn derive_enum(
     data_enum: &DataEnum,
-    token_type: &Ident,
+    attributes: &[Attribute],
+    token_type: &Type,
+    rule: &proc_macro2::TokenStream,
 ) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let infix_parse = infix_parse(data_enum, token_type, rule);
+
+    // operations are tried first wherever they're wrapped, so their operands aren't taken alone
+    let operation_arms: Vec<proc_macro2::TokenStream> = data_enum
+        .variants
+        .iter()
+        .enumerate()
+        .filter_map(|(index, variant)| {
+            let inner_type = bare_inner_type(variant)?;
+            let arms = dispatched_arms(variant, index);
+            Some(quote! { if <#inner_type as ::rcket::Node>::INFIX { #arms } })
+        })
+        .collect();
     let variant_match_arms: Vec<proc_macro2::TokenStream> = data_enum
         .variants
         .iter()
-        .flat_map(|variant| variant_arms(variant, token_type))
+        .enumerate()
+        .filter(|(_, variant)| !is_infix(variant) && !is_error_variant(variant))
+        .map(|(index, variant)| {
+            let arms = dispatched_arms(variant, index);
+            match bare_inner_type(variant) {
+                Some(inner_type) => {
+                    quote! { if !<#inner_type as ::rcket::Node>::INFIX { #arms } }
+                }
+                _ => arms,
+            }
+        })
         .collect();
+    // the table has a set for every variant, looked up by their index
+    let dispatch = if operation_arms.is_empty() && variant_match_arms.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            let first_table = ::rcket::first::table_of::<Self>();
+            let next_kind = tokens.first().map(::rcket::HasKind::kind);
+        }
+    };
 
-    (quote! { Self }, quote! { #(#variant_match_arms)* None })
+    // a delimited enum is also itself between the delimiters, like a parenthesized expression
+    let group_arm = Delimiters::from_attributes(attributes).map(|delimiters| {
+        let steps = delimiters.around(
+            quote! { let (value, tokens) = <Self as ::rcket::Node>::parse_one(tokens)?; },
+            &format_ident!("group"),
+        );
+        let check = token_check(&delimiters.open);
+        quote! {
+            if tokens.first().is_none_or(|token| #check)
+                && let Some(result) = ::rcket::parse::attempt(|| -> Option<_> { #steps Some((value, tokens)) })
+            {
+                return Some(result);
+            }
+        }
+    });
+
+    (
+        quote! { Self },
+        quote! {
+            #dispatch
+            #infix_parse
+            if ::rcket::parse::take_cut() {
+                return None;
+            }
+            #(#operation_arms)*
+            #(#variant_match_arms)*
+            #group_arm
+            ::rcket::parse::expected(tokens.len(), <Self as ::rcket::Node>::first);
+            None
+        },
+    )
+}
+
+/// The arms of `variant`, the enum's `index`th, only tried when the next token is one it can
+/// start with.
+fn dispatched_arms(variant: &Variant, index: usize) -> proc_macro2::TokenStream {
+    let arms = variant_arms(variant);
+    quote! {
+        if first_table.admits(#index, next_kind) {
+            #(#arms)*
+            if ::rcket::parse::take_cut() {
+                return None;
+            }
+        }
+    }
 }
 
-fn variant_arms(variant: &Variant, token_type: &Ident) -> Vec<proc_macro2::TokenStream> {
+fn variant_arms(variant: &Variant) -> Vec<proc_macro2::TokenStream> {
     let variant_name = &variant.ident;
 
     let attribute_arms: Vec<proc_macro2::TokenStream> = variant
@@ -137,10 +1110,10 @@ fn variant_arms(variant: &Variant, token_type: &Ident) -> Vec<proc_macro2::Token
         .filter_map(|attribute| {
             if attribute.path().is_ident("token") {
                 let path = attribute.parse_args::<Path>().ok()?;
-                Some(token_arm(variant_name, &path, token_type))
+                Some(token_arm(variant_name, &path))
             } else if attribute.path().is_ident("extract") {
                 let path = attribute.parse_args::<Path>().ok()?;
-                Some(extract_arm(variant_name, &path, token_type))
+                Some(extract_arm(variant_name, &path))
             } else {
                 None
             }
@@ -151,39 +1124,245 @@ fn variant_arms(variant: &Variant, token_type: &Ident) -> Vec<proc_macro2::Token
         return attribute_arms;
     }
 
-    if let Some(inner_type) = single_unnamed_field(variant) {
-        vec![bare_arm(variant_name, inner_type)]
-    } else {
-        vec![]
+    let sequence = parse_sequence(
+        &variant.fields,
+        quote! { Self::#variant_name },
+        Delimiters::from_attributes(&variant.attrs),
+    );
+    vec![quote! {
+        if let Some((result, rest)) = #sequence {
+            return Some((result, rest));
+        }
+    }]
+}
+
+fn is_infix(variant: &Variant) -> bool {
+    variant
+        .attrs
+        .iter()
+        .any(|attribute| attribute.path().is_ident("infix"))
+}
+
+/// The type of a variant that just wraps another node, like `BinaryOperation(BinaryOperation)`.
+fn bare_inner_type(variant: &Variant) -> Option<&Type> {
+    let has_attributes = |attributes: &[syn::Attribute]| {
+        attributes.iter().any(|attribute| {
+            attribute.path().is_ident("token") || attribute.path().is_ident("extract")
+        })
+    };
+    if has_attributes(&variant.attrs)
+        || is_infix(variant)
+        || Delimiters::from_attributes(&variant.attrs).is_some()
+    {
+        return None;
+    }
+    let inner_type = single_unnamed_field(variant)?;
+    let wrapped = unwrap_box(inner_type)
+        .or(unwrap_option(inner_type))
+        .or(unwrap_vec(inner_type));
+    if has_attributes(&variant.fields.iter().next()?.attrs) || wrapped.is_some() {
+        return None;
     }
+    Some(inner_type)
 }
 
-fn token_arm(variant_name: &Ident, path: &Path, token_type: &Ident) -> proc_macro2::TokenStream {
-    let first_segment_ident = &path.segments[0].ident;
-    let token_pattern = if first_segment_ident == "Symbol" {
-        quote! { #token_type::Symbol(#path) }
-    } else {
-        quote! { #token_type::Keyword(#path) }
+/// `From` impls for variants that just wrap another node, which operations use to nest themselves
+/// in their operand type. Types wrapped by more than one variant are skipped.
+fn from_impls(
+    data_enum: &DataEnum,
+    type_name: &Ident,
+    generics: &Generics,
+) -> proc_macro2::TokenStream {
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+    // a type parameter could be any type, so its impl could overlap with the others
+    let wrapped: Vec<(&Ident, &Type)> = data_enum
+        .variants
+        .iter()
+        .filter_map(|variant| Some((&variant.ident, bare_inner_type(variant)?)))
+        .filter(|(_, inner_type)| !mentions_type_parameter(inner_type, generics))
+        .collect();
+    let type_string = |ty: &Type| quote! { #ty }.to_string();
+    let impls = wrapped.iter().filter_map(|(variant_name, inner_type)| {
+        let count = wrapped
+            .iter()
+            .filter(|(_, other)| type_string(other) == type_string(inner_type))
+            .count();
+        if count > 1 {
+            return None;
+        }
+        Some(quote! {
+            impl #impl_generics ::std::convert::From<#inner_type> for #type_name #type_generics #where_clause {
+                fn from(value: #inner_type) -> Self {
+                    Self::#variant_name(value)
+                }
+            }
+        })
+    });
+    quote! { #(#impls)* }
+}
+
+/// Precedence climbing over the `#[infix(...)]` variants, each holding a left and right operand.
+///
+/// The first operand is parsed with this enum blocked at its position, so an operand type that
+/// wraps the enum falls back to its other variants instead of recursing forever. Right operands
+/// are parsed with a minimum precedence one higher than their operator's, which makes operators
+/// of the same precedence group to the left. Returns nothing unless at least one operator
+/// follows the first operand. Both states live in `rcket::parse`, keyed by the enum's `rule` id,
+/// so `#[node(memo)]` nodes can tell them apart.
+fn infix_parse(
+    data_enum: &DataEnum,
+    token_type: &Type,
+    rule: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let infix_variants: Vec<&Variant> = data_enum
+        .variants
+        .iter()
+        .filter(|variant| is_infix(variant))
+        .collect();
+    let Some(first) = infix_variants.first() else {
+        return quote! {};
+    };
+    let Some(first_field) = first.fields.iter().next() else {
+        emit_error!(first, "`#[infix]` variants need a left and a right operand");
+        return quote! {};
     };
+    let operand_type = unwrap_box(&first_field.ty).unwrap_or(&first_field.ty);
+
+    let operators: Vec<Path> = infix_variants
+        .iter()
+        .filter_map(|variant| {
+            variant
+                .attrs
+                .iter()
+                .find(|attribute| attribute.path().is_ident("infix"))
+                .and_then(|attribute| attribute.parse_args::<Path>().ok())
+        })
+        .collect();
+    let operator_names = First::union(operators.iter().map(First::token).collect()).names();
+
+    let operator_arms = infix_variants.iter().map(|variant| {
+        let variant_name = &variant.ident;
+        let operator = variant
+            .attrs
+            .iter()
+            .find(|attribute| attribute.path().is_ident("infix"))
+            .and_then(|attribute| attribute.parse_args::<Path>().ok());
+        let Some(operator) = operator else {
+            emit_error!(
+                variant,
+                "expected a token path like `#[infix(Symbol::Plus)]`"
+            );
+            return quote! {};
+        };
+        let precedence = variant
+            .attrs
+            .iter()
+            .find(|attribute| attribute.path().is_ident("prec"))
+            .map(|attribute| attribute.parse_args::<syn::LitInt>()?.base10_parse::<u32>())
+            .transpose()
+            .unwrap_or_else(|error| {
+                emit_error!(error.span(), "{}", error);
+                None
+            })
+            .unwrap_or(0);
+        if variant.fields.len() != 2 {
+            emit_error!(
+                variant,
+                "`#[infix]` variants need a left and a right operand"
+            );
+            return quote! {};
+        }
+        let wrap = |field: &syn::Field, value: proc_macro2::TokenStream| {
+            if unwrap_box(&field.ty).is_some() {
+                quote! { ::std::boxed::Box::new(#value) }
+            } else {
+                value
+            }
+        };
+        let mut fields = variant.fields.iter();
+        let left = wrap(fields.next().unwrap(), quote! { left });
+        let right = wrap(fields.next().unwrap(), quote! { right });
+        let first_token = projected_first_token(&operator);
+        quote! {
+            if let Some((#operator, rest)) = #first_token && #precedence >= min_precedence {
+                let build: fn(#operand_type, #operand_type) -> Self =
+                    |left, right| Self::#variant_name(#left, #right);
+                break 'operator Some((#precedence, rest, build));
+            }
+        }
+    });
+
     quote! {
-        if let Some((#token_pattern, rest)) = tokens.split_first() {
-            return Some((Self::#variant_name, rest));
+        let rule = #rule;
+        if !::rcket::parse::is_operand_start(rule, tokens) && let Some(result) = ::rcket::parse::attempt(|| -> Option<(Self, &[#token_type])> {
+            let min_precedence = ::rcket::parse::min_precedence(rule, tokens);
+            let first = ::rcket::parse::parse_operand(rule, tokens, || {
+                <#operand_type as ::rcket::Node>::parse_one(tokens)
+            });
+            let (first, mut tokens) = first?;
+            let mut first = Some(first);
+            let mut built: Option<Self> = None;
+            loop {
+                let operator = 'operator: {
+                    #(#operator_arms)*
+                    None
+                };
+                let Some((precedence, rest, build)) = operator else {
+                    ::rcket::parse::expected(tokens.len(), || #operator_names);
+                    break;
+                };
+                let right = ::rcket::parse::parse_right_operand(rule, rest, precedence + 1, || {
+                    <#operand_type as ::rcket::Node>::parse_one(rest)
+                });
+                let Some((right, rest)) = right else {
+                    if ::rcket::parse::take_cut() {
+                        return None;
+                    }
+                    break;
+                };
+                let left = match built.take() {
+                    Some(operation) => <#operand_type as ::std::convert::From<Self>>::from(operation),
+                    None => first.take().expect("the first operand is only used once"),
+                };
+                built = Some(build(left, right));
+                tokens = rest;
+            }
+            built.map(|operation| (operation, tokens))
+        }) {
+            return Some(result);
         }
     }
 }
 
-fn extract_arm(variant_name: &Ident, path: &Path, token_type: &Ident) -> proc_macro2::TokenStream {
+/// The first token and the rest, with the token projected to the enum `path` is a variant of, e.g.
+/// `Symbol` for `Symbol::Plus`, through the `Project` impls `derive(Lex)` generates. This works
+/// whatever the wrapper token's variants are called. The enum is inferred from the pattern the
+/// result is matched against, so generic token enums don't need their arguments spelled out.
+fn projected_first_token(path: &Path) -> proc_macro2::TokenStream {
+    if path.segments.len() < 2 {
+        emit_error!(path, "expected a token variant path like `Symbol::Plus`");
+    }
     quote! {
-        if let Some((#token_type::Literal(#path(value)), rest)) = tokens.split_first() {
-            return Some((Self::#variant_name(value.clone()), rest));
+        tokens
+            .split_first()
+            .and_then(|(first, rest)| Some((::rcket::Project::<_>::project(first)?, rest)))
+    }
+}
+
+fn token_arm(variant_name: &Ident, path: &Path) -> proc_macro2::TokenStream {
+    let first_token = projected_first_token(path);
+    quote! {
+        if let Some((#path, rest)) = #first_token {
+            return Some((Self::#variant_name, rest));
         }
     }
 }
 
-fn bare_arm(variant_name: &Ident, inner_type: &Type) -> proc_macro2::TokenStream {
+fn extract_arm(variant_name: &Ident, path: &Path) -> proc_macro2::TokenStream {
+    let first_token = projected_first_token(path);
     quote! {
-        if let Some((result, rest)) = <#inner_type as ::rcket::Node>::parse_one(tokens) {
-            return Some((Self::#variant_name(result), rest));
+        if let Some((#path(value), rest)) = #first_token {
+            return Some((Self::#variant_name(value.clone()), rest));
         }
     }
 }
@@ -198,10 +1377,23 @@ pub(crate) fn single_unnamed_field(variant: &Variant) -> Option<&Type> {
 }
 
 fn unwrap_box(field_type: &Type) -> Option<&Type> {
+    type_argument(field_type, "Box")
+}
+
+fn unwrap_vec(field_type: &Type) -> Option<&Type> {
+    type_argument(field_type, "Vec")
+}
+
+fn unwrap_option(field_type: &Type) -> Option<&Type> {
+    type_argument(field_type, "Option")
+}
+
+/// `T` in `wrapper<T>`, like `Box<T>`.
+fn type_argument<'a>(field_type: &'a Type, wrapper: &str) -> Option<&'a Type> {
     if let Type::Path(type_path) = field_type {
         let segments = &type_path.path.segments;
         if segments.len() == 1
-            && segments[0].ident == "Box"
+            && segments[0].ident == wrapper
             && let PathArguments::AngleBracketed(angle_arguments) = &segments[0].arguments
             && angle_arguments.args.len() == 1
             && let GenericArgument::Type(inner_type) = &angle_arguments.args[0]
@@ -212,99 +1404,96 @@ fn unwrap_box(field_type: &Type) -> Option<&Type> {
     None
 }
 
+fn is_bool_type(ty: &Type) -> bool {
+    matches!(ty, Type::Path(type_path) if type_path.qself.is_none() && type_path.path.is_ident("bool"))
+}
+
 fn is_unit_type(ty: &Type) -> bool {
-    if let Type::Path(type_path) = ty
-        && type_path.qself.is_none()
-        && type_path.path.segments.len() == 1
-    {
-        let segment = type_path.path.segments.first().unwrap();
-        if segment.ident == "()" && segment.arguments.is_empty() {
-            return true;
-        }
-    }
-    false
+    matches!(ty, Type::Tuple(tuple) if tuple.elems.is_empty())
 }
 
-fn display_impl_struct(data_struct: &DataStruct, type_name: &Ident) -> proc_macro2::TokenStream {
+fn display_impl_struct(
+    data_struct: &DataStruct,
+    type_name: &Ident,
+    generics: &Generics,
+) -> proc_macro2::TokenStream {
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
     let type_name_str = type_name.to_string();
 
-    let field_writes: Vec<proc_macro2::TokenStream> = match &data_struct.fields {
-        Fields::Unnamed(fields) => {
-            let non_token_fields: Vec<_> = fields
-                .unnamed
-                .iter()
-                .enumerate()
-                .filter(|(_, field)| {
-                    !field
-                        .attrs
-                        .iter()
-                        .any(|attribute| attribute.path().is_ident("token"))
-                        && !is_unit_type(&field.ty)
-                })
-                .collect();
-            non_token_fields
-                .iter()
-                .enumerate()
-                .map(|(i, (field_idx, _field))| {
-                    let original_index = Index::from(*field_idx);
-                    if i == 0 {
-                        quote! { write!(formatter, "{}", self.#original_index)?; }
-                    } else {
-                        quote! { write!(formatter, " {}", self.#original_index)?; }
-                    }
-                })
-                .collect()
-        }
-        Fields::Named(fields) => {
-            let non_token_fields: Vec<_> = fields
-                .named
-                .iter()
-                .filter(|field| {
-                    !field
-                        .attrs
-                        .iter()
-                        .any(|attribute| attribute.path().is_ident("token"))
-                })
-                .collect();
-            non_token_fields
-                .iter()
-                .enumerate()
-                .map(|(i, field)| {
-                    let field_name = field.ident.as_ref().unwrap();
-                    if unwrap_box(&field.ty).is_some() {
-                        if i == 0 {
-                            quote! { ::std::fmt::Display::fmt(self.#field_name.as_ref(), formatter)?; }
-                        } else {
-                            quote! { write!(formatter, " ")?;
-                                 ::std::fmt::Display::fmt(self.#field_name.as_ref(), formatter)?; }
-                        }
-                    } else if is_unit_type(&field.ty) {
-                        quote! {}
-                    } else {
-                        if i == 0 {
-                            quote! { write!(formatter, "{}", self.#field_name)?; }
-                        } else {
-                            quote! { write!(formatter, " {}", self.#field_name)?; }
-                        }
-                    }
-                })
-                .collect()
-        }
-        Fields::Unit => vec![],
-    };
+    let accessors = data_struct
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| match &field.ident {
+            Some(field_name) => quote! { &self.#field_name },
+            None => {
+                let index = Index::from(index);
+                quote! { &self.#index }
+            }
+        })
+        .collect();
+    let field_writes = field_writes(&data_struct.fields, accessors);
 
     quote! {
-        impl ::std::fmt::Display for #type_name {
+        impl #impl_generics ::std::fmt::Display for #type_name #type_generics #where_clause {
             fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
                 write!(formatter, "{} (", #type_name_str)?;
-                #(#field_writes)*
+                #field_writes
                 write!(formatter, ")")
             }
         }
     }
 }
 
-fn display_impl_enum(data_enum: &DataEnum, type_name: &Ident) -> proc_macro2::TokenStream {
+/// Writes the fields reached through `accessors` separated by spaces, leaving out token fields and
+/// optional fields that weren't there.
+fn field_writes(
+    fields: &Fields,
+    accessors: Vec<proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+    let writes = fields
+        .iter()
+        .zip(accessors)
+        .filter_map(|(field, accessor)| {
+            if has_attribute(field, "token")
+                || is_unit_type(&field.ty)
+                || is_phantom_data(&field.ty)
+            {
+                None
+            } else if unwrap_vec(&field.ty).is_some() {
+                Some(quote! {
+                    for value in #accessor {
+                        write!(formatter, "{}{}", separator, value)?;
+                        separator = " ";
+                    }
+                })
+            } else if unwrap_option(&field.ty).is_some() {
+                Some(quote! {
+                    if let Some(value) = #accessor {
+                        write!(formatter, "{}{}", separator, value)?;
+                        separator = " ";
+                    }
+                })
+            } else {
+                Some(quote! {
+                    write!(formatter, "{}{}", separator, #accessor)?;
+                    separator = " ";
+                })
+            }
+        });
+    quote! {
+        #[allow(unused_mut, unused_assignments)]
+        let mut separator = "";
+        #(#writes)*
+    }
+}
+
+fn display_impl_enum(
+    data_enum: &DataEnum,
+    type_name: &Ident,
+    generics: &Generics,
+) -> proc_macro2::TokenStream {
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
     let type_name_str = type_name.to_string();
 
     let match_arms: Vec<proc_macro2::TokenStream> = data_enum
@@ -323,20 +1512,48 @@ fn display_impl_enum(data_enum: &DataEnum, type_name: &Ident) -> proc_macro2::To
                 .iter()
                 .any(|attribute| attribute.path().is_ident("extract"));
 
-            if has_token {
+            if is_error_variant(variant) {
+                quote! { Self::#variant_name(..) => write!(formatter, "{} ({})", #type_name_str, #variant_name_str), }
+            } else if has_token {
                 quote! { Self::#variant_name => write!(formatter, #variant_name_str), }
             } else if has_extract {
                 quote! { Self::#variant_name(value) => write!(formatter, "{} ({} ({}))", #type_name_str, #variant_name_str, value), }
-            } else if single_unnamed_field(variant).is_some() {
+            } else if single_unnamed_field(variant)
+                .is_some_and(|field_type| unwrap_vec(field_type).is_none() && unwrap_option(field_type).is_none())
+            {
                 quote! { Self::#variant_name(inner) => write!(formatter, "{} ({})", #type_name_str, inner), }
+            } else if variant.fields.is_empty() {
+                quote! { Self::#variant_name { .. } => write!(formatter, #variant_name_str), }
             } else {
-                quote! { Self::#variant_name => write!(formatter, #variant_name_str), }
+                // like a struct, without the fields only matched as tokens
+                let bindings: Vec<Ident> = (0..variant.fields.len())
+                    .map(|index| format_ident!("field_{}", index))
+                    .collect();
+                let field_writes = field_writes(
+                    &variant.fields,
+                    bindings.iter().map(|binding| quote! { #binding }).collect(),
+                );
+                let pattern = match &variant.fields {
+                    Fields::Named(fields) => {
+                        let field_names = fields.named.iter().map(|field| &field.ident);
+                        quote! { Self::#variant_name { #(#field_names: #bindings),* } }
+                    }
+                    _ => quote! { Self::#variant_name(#(#bindings),*) },
+                };
+                quote! {
+                    #[allow(unused_variables)]
+                    #pattern => {
+                        write!(formatter, "{} ({} (", #type_name_str, #variant_name_str)?;
+                        #field_writes
+                        write!(formatter, "))")
+                    }
+                }
             }
         })
         .collect();
 
     quote! {
-        impl ::std::fmt::Display for #type_name {
+        impl #impl_generics ::std::fmt::Display for #type_name #type_generics #where_clause {
             fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
                 match self {
                     #(#match_arms)*
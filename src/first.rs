@@ -0,0 +1,215 @@
+use std::any::{Any, type_name};
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::sync::{Arc, OnceLock};
+
+use crate::{HasKind, Node, ProjectKind};
+
+/// The kinds of tokens something can start with, its FIRST set. It can start with any token when
+/// it can be empty, or when it's a node written by hand that doesn't tell its kinds.
+#[derive(Debug, Clone)]
+pub struct First<K> {
+    /// The kinds in the order they were added, which is the order errors name them in.
+    kinds: Vec<K>,
+    lookup: HashSet<K>,
+    any: bool,
+}
+
+impl<K: Copy + Eq + Hash + ToString> First<K> {
+    /// What can't start with any token, like an enum without variants.
+    pub fn empty() -> Self {
+        First {
+            kinds: vec![],
+            lookup: HashSet::new(),
+            any: false,
+        }
+    }
+
+    pub fn any() -> Self {
+        First {
+            any: true,
+            ..First::empty()
+        }
+    }
+
+    pub fn of_kinds(kinds: Option<Vec<K>>) -> Self {
+        match kinds {
+            Some(kinds) => First::union(kinds.into_iter().map(First::kind)),
+            None => First::any(),
+        }
+    }
+
+    fn kind(kind: K) -> Self {
+        First {
+            kinds: vec![kind],
+            lookup: HashSet::from([kind]),
+            any: false,
+        }
+    }
+
+    /// The kinds in all of `sets`, in order and without duplicates.
+    pub fn union(sets: impl IntoIterator<Item = First<K>>) -> Self {
+        let mut union = First::empty();
+        for set in sets {
+            union.any |= set.any;
+            for kind in set.kinds {
+                if union.lookup.insert(kind) {
+                    union.kinds.push(kind);
+                }
+            }
+        }
+        union
+    }
+
+    /// Whether something starting with these kinds can start with a token of `kind`.
+    pub fn admits(&self, kind: K) -> bool {
+        self.any || self.lookup.contains(&kind)
+    }
+
+    /// The kinds, or `None` when it can start with any token.
+    pub fn kinds(&self) -> Option<Vec<K>> {
+        (!self.any).then(|| self.kinds.clone())
+    }
+
+    /// The names of the kinds, or `None` when it can start with any token.
+    pub fn names(&self) -> Option<Vec<String>> {
+        if self.any {
+            return None;
+        }
+        Some(self.kinds.iter().map(ToString::to_string).collect())
+    }
+}
+
+/// The FIRST sets of a node and, for an enum, of each of its variants, in order.
+#[derive(Debug, Clone)]
+pub struct FirstTable<K> {
+    pub node: First<K>,
+    pub variants: Vec<First<K>>,
+}
+
+impl<K: Copy + Eq + Hash + ToString> FirstTable<K> {
+    pub fn node(node: First<K>) -> Self {
+        FirstTable {
+            node,
+            variants: vec![],
+        }
+    }
+
+    /// Called by `derive(Node)` enums for whether the variant at `index` can start with a token
+    /// of `kind`, which is `None` at the end of the input, where any variant that can be empty
+    /// still parses.
+    pub fn admits(&self, index: usize, kind: Option<K>) -> bool {
+        kind.is_none_or(|kind| self.variants[index].admits(kind))
+    }
+
+    /// How much the sets hold, for telling whether they grew.
+    fn len(&self) -> usize {
+        let sets = std::iter::once(&self.node).chain(&self.variants);
+        sets.map(|set| set.kinds.len() + usize::from(set.any)).sum()
+    }
+}
+
+/// The nodes whose tables are being computed, outermost first. A node that starts with itself,
+/// like an `#[infix]` enum through its operand, is computed again until its table stops growing,
+/// and where it comes up inside itself it counts as the table it had on the previous round,
+/// starting out empty.
+#[derive(Default)]
+pub struct Visiting {
+    nodes: Vec<Visit>,
+    /// The outermost of `nodes` that came up again since the innermost started.
+    lowest_cycle: usize,
+}
+
+struct Visit {
+    /// Nodes are told apart by their type names, which unlike `TypeId`s don't need the nodes to
+    /// be `'static`. Only lifetimes are left out of them, which FIRST sets don't depend on.
+    name: &'static str,
+    /// The table from the previous round, an `Arc<FirstTable<K>>`.
+    previous: Option<Box<dyn Any>>,
+    recurred: bool,
+}
+
+/// Called by the `first_table` of `derive(Node)` nodes, giving the table `build` computes from
+/// the tables of the node's fields. Nodes without type parameters keep it in `cache` once it's
+/// final, which it isn't when it depends on the table of a node outside of this one that's still
+/// being computed.
+pub fn table<N, K>(
+    cache: Option<&'static OnceLock<Box<dyn Any + Send + Sync>>>,
+    visiting: &mut Visiting,
+    build: impl Fn(&mut Visiting) -> FirstTable<K>,
+) -> Arc<FirstTable<K>>
+where
+    N: ?Sized,
+    K: Copy + Eq + Hash + ToString + Send + Sync + 'static,
+{
+    let cached = cache
+        .and_then(OnceLock::get)
+        .and_then(|table| table.downcast_ref::<Arc<FirstTable<K>>>());
+    if let Some(table) = cached {
+        return table.clone();
+    }
+
+    let name = type_name::<N>();
+    if let Some(depth) = visiting.nodes.iter().position(|visit| visit.name == name) {
+        visiting.lowest_cycle = visiting.lowest_cycle.min(depth);
+        let visit = &mut visiting.nodes[depth];
+        visit.recurred = true;
+        return previous_table(visit).unwrap_or_else(|| Arc::new(FirstTable::node(First::empty())));
+    }
+
+    let depth = visiting.nodes.len();
+    let outer_cycle = std::mem::replace(&mut visiting.lowest_cycle, usize::MAX);
+    visiting.nodes.push(Visit {
+        name,
+        previous: None,
+        recurred: false,
+    });
+    let table = loop {
+        let table = Arc::new(build(visiting));
+        let visit = &mut visiting.nodes[depth];
+        // Sets only grow from one round to the next, so one that didn't grow is final
+        let previous_len = previous_table::<K>(visit).map_or(0, |previous| previous.len());
+        if !visit.recurred || table.len() == previous_len {
+            break table;
+        }
+        visit.previous = Some(Box::new(table));
+        visit.recurred = false;
+    };
+    visiting.nodes.pop();
+    let lowest_cycle = visiting.lowest_cycle;
+    visiting.lowest_cycle = outer_cycle.min(lowest_cycle);
+
+    if lowest_cycle >= depth
+        && let Some(cache) = cache
+    {
+        let _ = cache.set(Box::new(table.clone()));
+    }
+    table
+}
+
+fn previous_table<K: 'static>(visit: &Visit) -> Option<Arc<FirstTable<K>>> {
+    let previous = visit.previous.as_ref()?;
+    previous.downcast_ref::<Arc<FirstTable<K>>>().cloned()
+}
+
+/// Called by `derive(Node)` nodes for the table they dispatch on and answer
+/// [`Node::starts_with`] and [`Node::first`] from.
+pub fn table_of<N>() -> Arc<FirstTable<<N::Token as HasKind>::Kind>>
+where
+    N: Node + ?Sized,
+    N::Token: HasKind,
+{
+    N::first_table(&mut Visiting::default())
+}
+
+/// Called by `derive(Node)` nodes for the kind of the token variant a path like `Symbol::Plus`
+/// names, as a kind of their tokens `T`. The enum `P` it's a variant of is inferred from
+/// `is_variant`, a pattern on it, and `kind` picks the variant's kind by name from `P`'s
+/// `{P}Kinds`.
+pub fn token<T, P>(_is_variant: fn(&P) -> bool, kind: fn(P::Kinds) -> P::Kind) -> First<T::Kind>
+where
+    T: ProjectKind<P>,
+    P: HasKind,
+{
+    First::kind(T::project_kind(kind(P::kinds())))
+}
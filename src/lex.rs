@@ -0,0 +1,285 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+
+use crate::{FileId, Span, Spanned, TokenPass};
+
+pub mod builtin;
+#[doc(hidden)]
+pub mod check;
+
+/// Tokens are lexed from input that lives for `'src`, so they can borrow from it (`&'src str`).
+///
+/// Text is lexed from `str` by default, `#[lex(bytes)]` lexes `[u8]` instead.
+pub trait Lex<'src, I: LexInput + ?Sized = str>: Sized {
+    /// Lexes one token from the start of `input`, failing when a pattern matched but its text
+    /// couldn't be converted into the variant's field. `previous` is the text of the token before
+    /// it, for `#[preceded_by]` conditions.
+    ///
+    /// `derive(Lex)` implements this. It calls [`Lex::lex_one`] by default, so lexers written by
+    /// hand only implement it to report conversion failures or look at the previous token.
+    fn try_lex_after(
+        previous: Option<&'src I>,
+        input: &'src I,
+    ) -> Result<Option<(Self, &'src I)>, InvalidToken> {
+        let _ = previous;
+        Ok(Self::lex_one(input))
+    }
+
+    fn try_lex_one(input: &'src I) -> Result<Option<(Self, &'src I)>, InvalidToken> {
+        Self::try_lex_after(None, input)
+    }
+
+    /// Lexes one token from the start of `input`, `None` when nothing can be lexed there or the
+    /// matched text failed to convert, which [`Lex::try_lex_one`] tells apart. Lexers that
+    /// implement [`Lex::try_lex_after`] implement this as `Self::try_lex_one(input).ok().flatten()`.
+    fn lex_one(input: &'src I) -> Option<(Self, &'src I)>;
+
+    /// Builds the token for a run of unlexable input, only enums with an `#[error]` variant have one.
+    fn error_token(_text: &'src I) -> Option<Self> {
+        None
+    }
+
+    /// How much input a pattern consumed before failing halfway, like an unterminated string.
+    fn partial_match_len(_input: &'src I) -> usize {
+        0
+    }
+
+    /// The patterns `derive(Lex)` tries in order, with those of wrapped enums in place of the
+    /// variants wrapping them, for `#[lex(check)]`. Lexers written by hand have none.
+    #[doc(hidden)]
+    fn patterns() -> Vec<check::Pattern> {
+        Vec::new()
+    }
+
+    /// Lexes the whole input into the tokens [`Lex::lex_spanned`] gives, without their spans.
+    /// Errors, unlexable input and values that failed to convert alike, are only kept as error
+    /// tokens when the enum has an `#[error]` variant, so use `lex_spanned` to report them.
+    fn lex(input: &'src I) -> Vec<Self> {
+        Self::lex_spanned(input).into_values()
+    }
+
+    /// Lexes the whole input, keeping each token's span and reporting unlexable input and values
+    /// that failed to convert. Erroneous input becomes an error token when the enum has an
+    /// `#[error]` variant and is skipped otherwise.
+    fn lex_spanned(input: &'src I) -> Lexed<Self> {
+        Self::lex_file(FileId::NONE, input)
+    }
+
+    /// Like [`Lex::lex_spanned`], with every span in `file` so tokens from different files of a
+    /// [`SourceMap`](crate::SourceMap) stay apart.
+    ///
+    /// Unlexable input is gathered into runs, which go on across whitespace as long as more
+    /// unlexable input follows and end at the next token or conversion error. Every position is
+    /// tried once, so lexing stays linear in the input, and each step takes at least one
+    /// character: an empty token is unlexable input and an empty error covers one character.
+    fn lex_file(file: FileId, input: &'src I) -> Lexed<Self> {
+        let mut lexed = Lexed {
+            tokens: Vec::new(),
+            errors: Vec::new(),
+        };
+        let mut remaining = input;
+        let mut previous = None;
+        // the start of the open unlexable run and the end of its last unlexable input
+        let mut run: Option<(usize, usize)> = None;
+        loop {
+            remaining = remaining.trim_start();
+            let start = input.len() - remaining.len();
+            // the run so far comes before the next token if it ends here
+            let run_text = run.map(|(run_start, run_end)| slice(input, run_start, run_end));
+            let attempt = if remaining.is_empty() {
+                None
+            } else {
+                // an empty token would be lexed forever, so it counts as unlexable input
+                match Self::try_lex_after(run_text.or(previous), remaining) {
+                    Ok(Some((_, rest))) if rest.len() == remaining.len() => Some(Ok(None)),
+                    attempt => Some(attempt),
+                }
+            };
+            if let Some(Ok(None)) = attempt {
+                remaining = match Self::partial_match_len(remaining) {
+                    0 => remaining.skip_one(),
+                    partial_len => remaining.split_at(partial_len).1,
+                };
+                let run_start = run.map_or(start, |(run_start, _)| run_start);
+                run = Some((run_start, input.len() - remaining.len()));
+                continue;
+            }
+
+            if let (Some((run_start, _)), Some(text)) = (run.take(), run_text) {
+                lexed.push_error(
+                    file,
+                    run_start,
+                    text,
+                    "unrecognized input".to_string(),
+                    Self::error_token(text),
+                );
+            }
+            match attempt {
+                None => break,
+                Some(Ok(Some((token, rest)))) => {
+                    let end = input.len() - rest.len();
+                    lexed
+                        .tokens
+                        .push(Spanned::new(token, Span::new(start, end).with_file(file)));
+                    previous = Some(remaining.split_at(end - start).0);
+                    remaining = rest;
+                }
+                Some(Err(invalid)) => {
+                    // an error takes at least one character, so lexing goes on after it
+                    let len = match invalid.len {
+                        0 => remaining.len() - remaining.skip_one().len(),
+                        len => len,
+                    };
+                    let (text, rest) = remaining.split_at(len);
+                    lexed.push_error(file, start, text, invalid.message, Self::error_token(text));
+                    previous = Some(text);
+                    remaining = rest;
+                }
+                Some(Ok(None)) => unreachable!("unlexable input extends the run"),
+            }
+        }
+        lexed
+    }
+}
+
+/// The input from byte `start` up to `end`.
+fn slice<I: LexInput + ?Sized>(input: &I, start: usize, end: usize) -> &I {
+    input.split_at(end).0.split_at(start).1
+}
+
+/// A pattern matched `len` bytes of input but the text couldn't become a token, for example an
+/// integer literal that overflows its field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidToken {
+    pub len: usize,
+    pub message: String,
+}
+
+/// Input that couldn't be lexed, either because no pattern matched it or because its value
+/// failed to convert.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub span: Span,
+    pub text: String,
+    pub message: String,
+}
+
+impl Display for LexError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "{:?} at {}..{}: {}",
+            self.text, self.span.start, self.span.end, self.message
+        )
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// Every token of an input with its span, plus the errors found along the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lexed<T> {
+    pub tokens: Vec<Spanned<T>>,
+    pub errors: Vec<LexError>,
+}
+
+impl<T> Lexed<T> {
+    /// Reports the erroneous `text` at `start`, keeping its error token if there is one.
+    fn push_error<I: LexInput + ?Sized>(
+        &mut self,
+        file: FileId,
+        start: usize,
+        text: &I,
+        message: String,
+        token: Option<T>,
+    ) {
+        let span = Span::new(start, start + text.len()).with_file(file);
+        self.errors.push(LexError {
+            span,
+            text: text.to_text().into_owned(),
+            message,
+        });
+        if let Some(token) = token {
+            self.tokens.push(Spanned::new(token, span));
+        }
+    }
+
+    /// Runs a [`TokenPass`] over the tokens, keeping the errors.
+    pub fn apply(mut self, mut pass: impl TokenPass<T>) -> Self {
+        self.tokens = pass.run(self.tokens);
+        self
+    }
+
+    /// The tokens without their spans, ready for `Node::parse`.
+    pub fn into_values(self) -> Vec<T> {
+        self.tokens.into_iter().map(|token| token.value).collect()
+    }
+}
+
+/// Input that can be lexed, either text or raw bytes.
+pub trait LexInput {
+    fn trim_start(&self) -> &Self;
+
+    /// Skips one unlexable character, or byte for byte input.
+    fn skip_one(&self) -> &Self;
+
+    fn split_at(&self, index: usize) -> (&Self, &Self);
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The input as text for diagnostics, invalid UTF-8 is replaced.
+    fn to_text(&self) -> Cow<'_, str>;
+}
+
+impl LexInput for str {
+    fn trim_start(&self) -> &Self {
+        str::trim_start(self)
+    }
+
+    fn skip_one(&self) -> &Self {
+        let skip_to = self
+            .char_indices()
+            .nth(1)
+            .map(|(index, _)| index)
+            .unwrap_or(self.len());
+        &self[skip_to..]
+    }
+
+    fn split_at(&self, index: usize) -> (&Self, &Self) {
+        str::split_at(self, index)
+    }
+
+    fn len(&self) -> usize {
+        str::len(self)
+    }
+
+    fn to_text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl LexInput for [u8] {
+    fn trim_start(&self) -> &Self {
+        self.trim_ascii_start()
+    }
+
+    fn skip_one(&self) -> &Self {
+        self.get(1..).unwrap_or_default()
+    }
+
+    fn split_at(&self, index: usize) -> (&Self, &Self) {
+        <[u8]>::split_at(self, index)
+    }
+
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn to_text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self)
+    }
+}
@@ -0,0 +1,412 @@
+//! Matchers for the tokens most grammars share, used from `derive(Lex)` with `#[matcher(...)]`.
+//!
+//! Each matcher looks at the start of the input and returns the token's value and length, or
+//! `Ok(None)` when the input doesn't start with one. Malformed tokens, like an unterminated string
+//! or a digit out of range for its base, are reported as an [`InvalidToken`] covering the whole
+//! token so lexing resumes after it.
+//!
+//! They all lex text, so `#[lex(bytes)]` enums can't use them.
+
+use crate::InvalidToken;
+
+/// The result of a matcher: nothing, a value with the number of bytes it spans, or a malformed
+/// token.
+pub type Matched<T> = Result<Option<(T, usize)>, InvalidToken>;
+
+fn invalid<T>(len: usize, message: impl Into<String>) -> Matched<T> {
+    Err(InvalidToken {
+        len,
+        message: message.into(),
+    })
+}
+
+/// Length of the digits and `_` separators at the start of `input`.
+fn digits_len(input: &str, radix: u32) -> usize {
+    input
+        .find(|character: char| !(character.is_digit(radix) || character == '_'))
+        .unwrap_or(input.len())
+}
+
+/// Length of the identifier characters at the start of `input`, so `0b102` or `1.5px` is reported
+/// as one token.
+fn word_len(input: &str) -> usize {
+    input
+        .find(|character: char| !(character.is_alphanumeric() || character == '_'))
+        .unwrap_or(input.len())
+}
+
+fn starts_with_digit(input: &str) -> bool {
+    input.starts_with(|character: char| character.is_ascii_digit())
+}
+
+/// Integer literals in decimal `1_000`, hexadecimal `0xFF`, octal `0o17` and binary `0b1010`, with
+/// `_` separators after the first digit or the prefix.
+///
+/// The value is a `u128`, which converts into any integer field and reports values that don't fit.
+/// Signs are left to a `-` token. Declare [`float`] first, since `1.5` starts with the integer `1`.
+pub fn int(input: &str) -> Matched<u128> {
+    if !starts_with_digit(input) {
+        return Ok(None);
+    }
+    let (radix, prefix_len) = match input.get(..2) {
+        Some("0x" | "0X") => (16, 2),
+        Some("0o" | "0O") => (8, 2),
+        Some("0b" | "0B") => (2, 2),
+        _ => (10, 0),
+    };
+    let len = word_len(input);
+    let digits = &input[prefix_len..len];
+    if !digits.contains(|character: char| character != '_') {
+        return invalid(
+            len,
+            format!("expected digits after {:?}", &input[..prefix_len]),
+        );
+    }
+
+    let mut value: u128 = 0;
+    for character in digits.chars().filter(|&character| character != '_') {
+        let Some(digit) = character.to_digit(radix) else {
+            return invalid(
+                len,
+                format!("invalid digit {character:?} in base {radix} literal"),
+            );
+        };
+        value = match value
+            .checked_mul(u128::from(radix))
+            .and_then(|value| value.checked_add(u128::from(digit)))
+        {
+            Some(value) => value,
+            None => return invalid(len, "integer literal is too large"),
+        };
+    }
+    Ok(Some((value, len)))
+}
+
+/// Floating point literals with a fraction, an exponent or both: `1.5`, `1e10`, `2.5E-3`,
+/// `1_000.0`.
+///
+/// Plain integers aren't matched, and neither is `1.` without fraction digits, so ranges like
+/// `1..2` and method calls like `1.max(2)` still lex. Values out of range become infinity, as
+/// IEEE 754 rounds them.
+pub fn float(input: &str) -> Matched<f64> {
+    if !starts_with_digit(input) {
+        return Ok(None);
+    }
+    let mut len = digits_len(input, 10);
+    let mut has_fraction = false;
+    if let Some(fraction) = input[len..].strip_prefix('.')
+        && starts_with_digit(fraction)
+    {
+        len += 1 + digits_len(fraction, 10);
+        has_fraction = true;
+    }
+
+    let mut has_exponent = false;
+    if let Some(exponent) = input[len..].strip_prefix(['e', 'E']) {
+        let sign_len = usize::from(exponent.starts_with(['+', '-']));
+        let exponent_len = digits_len(&exponent[sign_len..], 10);
+        let exponent_digits = &exponent[sign_len..sign_len + exponent_len];
+        if exponent_digits.contains(|character: char| character.is_ascii_digit()) {
+            len += 1 + sign_len + exponent_len;
+            has_exponent = true;
+        } else if has_fraction {
+            return invalid(
+                len + 1 + sign_len + exponent_len,
+                "expected at least one digit in the exponent",
+            );
+        }
+    }
+    if !has_fraction && !has_exponent {
+        return Ok(None);
+    }
+
+    let suffix_len = word_len(&input[len..]);
+    if suffix_len > 0 {
+        return invalid(
+            len + suffix_len,
+            format!(
+                "invalid suffix {:?} on float literal",
+                &input[len..len + suffix_len]
+            ),
+        );
+    }
+    match input[..len].replace('_', "").parse::<f64>() {
+        Ok(value) => Ok(Some((value, len))),
+        Err(error) => invalid(len, error.to_string()),
+    }
+}
+
+/// Length of the hexadecimal digits at the start of `input`, at most `max` of them.
+fn hex_digits_len(input: &str, max: usize) -> usize {
+    input
+        .find(|character: char| !character.is_ascii_hexdigit())
+        .unwrap_or(input.len())
+        .min(max)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EscapeStyle {
+    C,
+    Rust,
+}
+
+/// A successfully read escape: the character it stands for, or `None` for a Rust line
+/// continuation, and how many bytes after the backslash it spans.
+type Escape = (Option<char>, usize);
+
+/// Reads the escape sequence after a backslash, returning an error message and the length to skip
+/// if it's invalid.
+fn escape(rest: &str, style: EscapeStyle) -> Result<Escape, (String, usize)> {
+    let Some(character) = rest.chars().next() else {
+        return Err(("unterminated escape".to_string(), 0));
+    };
+    let simple = match character {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '\\' => Some('\\'),
+        '\'' => Some('\''),
+        '"' => Some('"'),
+        '0' if style == EscapeStyle::Rust => Some('\0'),
+        'a' if style == EscapeStyle::C => Some('\x07'),
+        'b' if style == EscapeStyle::C => Some('\x08'),
+        'f' if style == EscapeStyle::C => Some('\x0C'),
+        'v' if style == EscapeStyle::C => Some('\x0B'),
+        '?' if style == EscapeStyle::C => Some('?'),
+        _ => None,
+    };
+    if let Some(value) = simple {
+        return Ok((Some(value), 1));
+    }
+
+    // every scan stops at the first character that can't belong to the escape, so the closing
+    // quote is never part of one
+    match (character, style) {
+        ('0'..='7', EscapeStyle::C) => {
+            let len = rest
+                .find(|character: char| !character.is_digit(8))
+                .unwrap_or(rest.len())
+                .min(3);
+            match u8::from_str_radix(&rest[..len], 8) {
+                Ok(code) => Ok((Some(char::from(code)), len)),
+                Err(_) => Err(("octal escape must be at most \\377".to_string(), len)),
+            }
+        }
+        ('x', EscapeStyle::C) => {
+            let len = 1 + hex_digits_len(&rest[1..], usize::MAX);
+            if len == 1 {
+                return Err(("expected hexadecimal digits after \\x".to_string(), len));
+            }
+            match u8::from_str_radix(&rest[1..len], 16) {
+                Ok(code) => Ok((Some(char::from(code)), len)),
+                Err(_) => Err(("hexadecimal escape must be at most \\xFF".to_string(), len)),
+            }
+        }
+        ('x', EscapeStyle::Rust) => {
+            let len = 1 + hex_digits_len(&rest[1..], 2);
+            if len < 3 {
+                return Err(("hexadecimal escape must have two digits".to_string(), len));
+            }
+            match u8::from_str_radix(&rest[1..len], 16) {
+                Ok(code) if code <= 0x7F => Ok((Some(char::from(code)), len)),
+                _ => Err(("hexadecimal escape must be at most \\x7F".to_string(), len)),
+            }
+        }
+        ('u', EscapeStyle::Rust) => {
+            let Some(braced) = rest[1..].strip_prefix('{') else {
+                return Err(("expected `{` after \\u".to_string(), 1));
+            };
+            let digits_len = braced
+                .find(|character: char| !(character.is_ascii_hexdigit() || character == '_'))
+                .unwrap_or(braced.len());
+            if !braced[digits_len..].starts_with('}') {
+                return Err(("unterminated unicode escape".to_string(), 2 + digits_len));
+            }
+            let len = 2 + digits_len + 1;
+            let digits = braced[..digits_len].replace('_', "");
+            if digits.is_empty() || digits.len() > 6 {
+                return Err(("unicode escape must have 1 to 6 digits".to_string(), len));
+            }
+            u32::from_str_radix(&digits, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .map(|value| (Some(value), len))
+                .ok_or_else(|| ("invalid unicode escape".to_string(), len))
+        }
+        ('\n', EscapeStyle::Rust) => {
+            let whitespace_len = rest[1..]
+                .find(|character: char| !character.is_whitespace())
+                .unwrap_or(rest.len() - 1);
+            Ok((None, 1 + whitespace_len))
+        }
+        _ => Err((
+            format!("unknown escape \\{}", character.escape_debug()),
+            character.len_utf8(),
+        )),
+    }
+}
+
+/// Reads a quoted literal with escapes. An invalid escape is reported once the closing quote is
+/// found, so the error covers the whole literal.
+fn quoted(input: &str, quote: char, style: EscapeStyle, multiline: bool) -> Matched<String> {
+    let Some(mut rest) = input.strip_prefix(quote) else {
+        return Ok(None);
+    };
+    let mut value = String::new();
+    let mut error = None;
+    while let Some(character) = rest.chars().next() {
+        let position = input.len() - rest.len();
+        rest = &rest[character.len_utf8()..];
+        if character == quote {
+            let len = position + character.len_utf8();
+            return match error {
+                Some(message) => invalid(len, message),
+                None => Ok(Some((value, len))),
+            };
+        } else if character == '\n' && !multiline {
+            return invalid(position, format!("unterminated {quote} literal"));
+        } else if character == '\\' {
+            match escape(rest, style) {
+                Ok((escaped, len)) => {
+                    value.extend(escaped);
+                    rest = &rest[len..];
+                }
+                Err((message, len)) => {
+                    error.get_or_insert(message);
+                    rest = &rest[len..];
+                }
+            }
+        } else {
+            value.push(character);
+        }
+    }
+    invalid(input.len(), format!("unterminated {quote} literal"))
+}
+
+/// Exactly one character between quotes.
+fn single_char(matched: Matched<String>) -> Matched<char> {
+    let Some((value, len)) = matched? else {
+        return Ok(None);
+    };
+    let mut characters = value.chars();
+    match (characters.next(), characters.next()) {
+        (Some(character), None) => Ok(Some((character, len))),
+        (None, _) => invalid(len, "empty character literal"),
+        (Some(_), Some(_)) => invalid(len, "character literal must contain one character"),
+    }
+}
+
+/// C string literals like `"line\n"`, with the escapes `\n \t \r \a \b \f \v \\ \' \" \?`, octal
+/// `\101` and hexadecimal `\x41`. The value has its escapes resolved, and a line break before the
+/// closing quote makes the literal unterminated.
+pub fn c_string(input: &str) -> Matched<String> {
+    quoted(input, '"', EscapeStyle::C, false)
+}
+
+/// C character literals like `'a'` or `'\n'`, with the same escapes as [`c_string`].
+pub fn c_char(input: &str) -> Matched<char> {
+    single_char(quoted(input, '\'', EscapeStyle::C, false))
+}
+
+/// Rust string literals like `"tab\t"`, with the escapes `\n \t \r \0 \\ \' \"`, `\x7F` and
+/// `\u{1F600}`. Strings may span lines, and a backslash at the end of a line skips the line break
+/// and the next line's leading whitespace.
+pub fn rust_string(input: &str) -> Matched<String> {
+    quoted(input, '"', EscapeStyle::Rust, true)
+}
+
+/// Rust raw string literals like `r"C:\path"` or `r#"say "hi""#`, without escapes. The value
+/// borrows the text between the quotes.
+pub fn rust_raw_string(input: &str) -> Matched<&str> {
+    let Some(after_r) = input.strip_prefix('r') else {
+        return Ok(None);
+    };
+    let hashes = after_r.len() - after_r.trim_start_matches('#').len();
+    let Some(body) = after_r[hashes..].strip_prefix('"') else {
+        return Ok(None);
+    };
+    let terminator = format!("\"{}", "#".repeat(hashes));
+    match body.find(&terminator) {
+        Some(end) => Ok(Some((
+            &body[..end],
+            1 + hashes + 1 + end + terminator.len(),
+        ))),
+        None => invalid(input.len(), "unterminated raw string"),
+    }
+}
+
+/// Rust character literals like `'a'`, `'\''` or `'\u{E9}'`, with the same escapes as
+/// [`rust_string`]. A quote that isn't closed after one character, like the lifetime `'a`, isn't
+/// matched.
+pub fn rust_char(input: &str) -> Matched<char> {
+    if let Some(after_quote) = input.strip_prefix('\'')
+        && let Some(character) = after_quote.chars().next()
+        && character != '\\'
+        && character != '\''
+        && !after_quote[character.len_utf8()..].starts_with('\'')
+    {
+        return Ok(None);
+    }
+    single_char(quoted(input, '\'', EscapeStyle::Rust, false))
+}
+
+/// Unicode identifiers as Rust defines them: a `XID_Start` character or `_`, followed by any
+/// `XID_Continue` characters, so `größe` and `変数` are identifiers but `1st` isn't.
+pub fn identifier(input: &str) -> Matched<&str> {
+    let mut characters = input.char_indices();
+    match characters.next() {
+        Some((_, first)) if first == '_' || unicode_ident::is_xid_start(first) => {}
+        _ => return Ok(None),
+    }
+    let len = characters
+        .find(|&(_, character)| !unicode_ident::is_xid_continue(character))
+        .map_or(input.len(), |(index, _)| index);
+    Ok(Some((&input[..len], len)))
+}
+
+/// `// line comments` up to the end of the line. The value is the text after `//`, without the
+/// line break.
+pub fn line_comment(input: &str) -> Matched<&str> {
+    let Some(body) = input.strip_prefix("//") else {
+        return Ok(None);
+    };
+    let len = body.find('\n').unwrap_or(body.len());
+    let text = &body[..len];
+    Ok(Some((text.strip_suffix('\r').unwrap_or(text), 2 + len)))
+}
+
+/// C `/* block comments */`, which end at the first `*/`. The value is the text between the
+/// delimiters.
+pub fn block_comment(input: &str) -> Matched<&str> {
+    let Some(body) = input.strip_prefix("/*") else {
+        return Ok(None);
+    };
+    match body.find("*/") {
+        Some(end) => Ok(Some((&body[..end], 2 + end + 2))),
+        None => invalid(input.len(), "unterminated block comment"),
+    }
+}
+
+/// Rust `/* block comments */`, which nest: `/* a /* b */ c */` is one comment.
+pub fn nested_block_comment(input: &str) -> Matched<&str> {
+    let Some(body) = input.strip_prefix("/*") else {
+        return Ok(None);
+    };
+    let mut depth = 1;
+    let mut index = 0;
+    while index < body.len() {
+        if body[index..].starts_with("/*") {
+            depth += 1;
+            index += 2;
+        } else if body[index..].starts_with("*/") {
+            depth -= 1;
+            if depth == 0 {
+                return Ok(Some((&body[..index], 2 + index + 2)));
+            }
+            index += 2;
+        } else {
+            index += body[index..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    invalid(input.len(), "unterminated block comment")
+}
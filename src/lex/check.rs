@@ -0,0 +1,171 @@
+//! The part of `#[lex(check)]` that runs as a test, for enums wrapping other `Lex` enums, whose
+//! patterns the derive can't see. The test checks the patterns of the whole enum in the order
+//! they're tried, with those of wrapped enums in place of the variants wrapping them.
+
+use std::collections::HashSet;
+
+use regex::bytes::Regex;
+use regex_automata::dfa::{Automaton, StartKind, dense};
+use regex_automata::{Anchored, Input, MatchKind};
+
+/// A pattern a `derive(Lex)` enum lexes with.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    /// The variant it lexes, like `Keyword::Int`.
+    pub variant: &'static str,
+    pub matcher: PatternMatcher,
+    /// The exact text it matches, if it only ever matches one string.
+    pub text: Option<&'static str>,
+    /// Lookaround conditions can reject a match, so the pattern doesn't always win.
+    pub conditional: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum PatternMatcher {
+    Token(&'static str),
+    Regex {
+        pattern: &'static str,
+        word_boundary: bool,
+    },
+}
+
+/// The patterns that can never be lexed because one tried before them always wins, each as an
+/// error message like those `#[lex(check)]` reports when the enum is compiled.
+pub fn conflicts(patterns: &[Pattern]) -> Vec<String> {
+    let mut conflicts = vec![];
+    for (index, pattern) in patterns.iter().enumerate() {
+        let mut earlier = patterns[..index]
+            .iter()
+            .filter(|earlier| earlier.variant != pattern.variant && !earlier.conditional);
+        if let Some(text) = pattern.text {
+            if let Some(earlier) =
+                earlier.find(|earlier| match_len(&earlier.matcher, text).is_some())
+            {
+                conflicts.push(format!(
+                    "pattern {:?} of `{}` is unreachable, it is always lexed as `{}`",
+                    text, pattern.variant, earlier.variant
+                ));
+            }
+            continue;
+        }
+
+        let PatternMatcher::Regex { pattern: regex, .. } = pattern.matcher else {
+            continue;
+        };
+        let covering = earlier.find_map(|earlier| {
+            let PatternMatcher::Regex {
+                pattern: earlier_regex,
+                word_boundary,
+            } = earlier.matcher
+            else {
+                return None;
+            };
+            if earlier_regex == regex {
+                return Some((earlier, Coverage::Equal));
+            }
+            // an earlier pattern that has to end at a word boundary can give way to a later one
+            // matching a different length
+            if word_boundary {
+                return None;
+            }
+            Some((earlier, coverage(earlier_regex, regex)?))
+        });
+        match covering {
+            Some((earlier, Coverage::Equal)) => conflicts.push(format!(
+                "`{}` and `{}` match the same text, `{}` always wins",
+                earlier.variant, pattern.variant, earlier.variant
+            )),
+            Some((earlier, Coverage::Wider)) => conflicts.push(format!(
+                "pattern {:?} of `{}` is unreachable, everything it matches is lexed as `{}`",
+                regex, pattern.variant, earlier.variant
+            )),
+            None => {}
+        }
+    }
+    conflicts
+}
+
+/// How much of `text` a pattern would consume, mirroring the generated lexing arms.
+fn match_len(matcher: &PatternMatcher, text: &str) -> Option<usize> {
+    let (length, word_boundary) = match matcher {
+        PatternMatcher::Token(token) => {
+            if !text.starts_with(token) {
+                return None;
+            }
+            let is_word = token
+                .chars()
+                .all(|character| character.is_alphabetic() || character == '_');
+            (token.len(), is_word)
+        }
+        PatternMatcher::Regex {
+            pattern,
+            word_boundary,
+        } => {
+            let regex = Regex::new(&format!("^(?:{pattern})")).ok()?;
+            (regex.find(text.as_bytes())?.end(), *word_boundary)
+        }
+    };
+    let rest = text.get(length..)?;
+    let at_boundary =
+        !rest.starts_with(|character: char| character.is_alphanumeric() || character == '_');
+    (length > 0 && (!word_boundary || at_boundary)).then_some(length)
+}
+
+/// How an earlier regex covers a later one that can never win over it.
+enum Coverage {
+    Equal,
+    /// The earlier one matches everything the later one does and more.
+    Wider,
+}
+
+/// Whether `earlier` matches every text `later` does, found by walking their DFAs side by side
+/// over every byte. `None` when it doesn't, or when either can't be built as a DFA, like with
+/// Unicode word boundaries.
+fn coverage(earlier: &str, later: &str) -> Option<Coverage> {
+    let build = |pattern: &str| {
+        dense::Builder::new()
+            .configure(
+                dense::Config::new()
+                    .match_kind(MatchKind::All)
+                    .start_kind(StartKind::Anchored),
+            )
+            .build(pattern)
+            .ok()
+    };
+    let (earlier_dfa, later_dfa) = (build(earlier)?, build(later)?);
+    let input = Input::new("").anchored(Anchored::Yes);
+    let start = (
+        earlier_dfa.start_state_forward(&input).ok()?,
+        later_dfa.start_state_forward(&input).ok()?,
+    );
+
+    let mut seen = HashSet::from([start]);
+    let mut pending = vec![start];
+    let mut equal = true;
+    while let Some((earlier_state, later_state)) = pending.pop() {
+        // matches are reported a byte late, so whether the text so far matches shows at its end
+        let earlier_matches = earlier_dfa.is_match_state(earlier_dfa.next_eoi_state(earlier_state));
+        let later_matches = later_dfa.is_match_state(later_dfa.next_eoi_state(later_state));
+        if later_matches && !earlier_matches {
+            return None;
+        }
+        equal &= earlier_matches == later_matches;
+        if later_dfa.is_dead_state(later_state) && !equal {
+            continue;
+        }
+        for byte in 0..=u8::MAX {
+            let next = (
+                earlier_dfa.next_state(earlier_state, byte),
+                later_dfa.next_state(later_state, byte),
+            );
+            if seen.insert(next) {
+                pending.push(next);
+            }
+        }
+    }
+    Some(if equal {
+        Coverage::Equal
+    } else {
+        Coverage::Wider
+    })
+}
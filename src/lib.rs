@@ -1,42 +1,153 @@
+#![forbid(unsafe_code)]
+
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::sync::Arc;
+
 pub use rcket_macros::{Lex, Node};
+// for `derive(Lex)`, so crates using it don't need their own `regex` dependency
+#[doc(hidden)]
+pub use regex;
+
+pub mod first;
+pub mod lex;
+mod line_index;
+pub mod parse;
+pub mod pass;
+mod source_map;
+mod span;
+pub mod tree;
+
+pub use lex::{InvalidToken, Lex, LexError, LexInput, Lexed};
+pub use line_index::{LineCol, LineIndex, Utf16Position};
+pub use parse::{ParseError, ParseErrorKind};
+pub use pass::TokenPass;
+pub use source_map::{FileId, Location, SourceMap};
+pub use span::{Span, Spanned};
 
 pub trait Node {
     type Token;
     type Output;
+
+    /// Whether this is an enum of `#[infix]` operations. Enums wrapping it try it before their
+    /// other variants, so `5 + 2` isn't parsed as just the operand `5`.
+    const INFIX: bool = false;
+
+    /// Whether the node can start with `token`. Enums only try the variants that can start with
+    /// the next token. It's true when that can't be told, like for nodes that can be empty.
+    fn starts_with(token: &Self::Token) -> bool {
+        let _ = token;
+        true
+    }
+
+    /// The names of the token kinds the node can start with, its FIRST set, which are the tokens
+    /// an error expects when an enum has no variant starting with the next one. `None` when they
+    /// aren't known.
+    fn first() -> Option<Vec<String>> {
+        None
+    }
+
+    /// The kinds of the tokens the node can start with, or `None` when it can start with any.
+    /// Nodes written by hand return them so the enums they're in can skip them like derived ones.
+    fn first_kinds() -> Option<Vec<<Self::Token as HasKind>::Kind>>
+    where
+        Self::Token: HasKind,
+    {
+        None
+    }
+
+    /// The FIRST sets of the node and of its variants, which `derive(Node)` computes once from
+    /// those of its fields. Nodes in `visiting` are being computed already.
+    #[doc(hidden)]
+    fn first_table(
+        visiting: &mut first::Visiting,
+    ) -> Arc<first::FirstTable<<Self::Token as HasKind>::Kind>>
+    where
+        Self::Token: HasKind,
+    {
+        let _ = visiting;
+        Arc::new(first::FirstTable::node(first::First::of_kinds(
+            Self::first_kinds(),
+        )))
+    }
+
     fn parse_one(tokens: &[Self::Token]) -> Option<(Self::Output, &[Self::Token])>;
 
     fn parse(tokens: &[Self::Token]) -> Option<Self::Output> {
-        let (result, rest) = Self::parse_one(tokens)?;
+        let (result, rest) = parse::scoped(tokens, || Self::parse_one(tokens))?;
         if rest.is_empty() { Some(result) } else { None }
     }
-}
 
-pub trait Lex: Sized {
-    fn lex_one(input: &str) -> Option<(Self, &str)>;
-
-    fn lex(input: &str) -> Vec<Self> {
-        let mut tokens = Vec::new();
-        let mut remaining = input;
-        loop {
-            remaining = remaining.trim_start();
-            if remaining.is_empty() {
-                break;
-            }
-            match Self::lex_one(remaining) {
-                Some((token, rest)) => {
-                    tokens.push(token);
-                    remaining = rest;
-                }
-                None => {
-                    let skip_to = remaining
-                        .char_indices()
-                        .nth(1)
-                        .map(|(index, _)| index)
-                        .unwrap_or(remaining.len());
-                    remaining = &remaining[skip_to..];
-                }
-            }
+    /// Like [`Node::parse`], reporting where parsing failed: at an unclosed delimiter, or the
+    /// first token that couldn't be parsed, whichever is further, with the tokens expected there.
+    fn try_parse(tokens: &[Self::Token]) -> Result<Self::Output, ParseError> {
+        let (result, failure) = parse::track(tokens, || Self::parse_one(tokens));
+        match result {
+            Some((output, [])) => Ok(output),
+            result => Err(parse::error(
+                tokens.len(),
+                result.map(|(_, rest)| rest.len()),
+                failure,
+            )),
         }
-        tokens
+    }
+
+    /// Parses with `#[recover]` enums skipping what they can't parse, giving the tree parsed
+    /// around those regions, if any, with every error met in order. It ends with the error
+    /// [`Node::try_parse`] would give when the tokens weren't all parsed.
+    fn parse_with_errors(tokens: &[Self::Token]) -> (Option<Self::Output>, Vec<ParseError>) {
+        parse::recovering(tokens, || Self::parse_one(tokens))
+    }
+
+    /// Parses spanned tokens, like those from `SourceMap::lex`, giving the result the span from
+    /// its first token to its last, in their file. The tokens are taken so their values can be
+    /// moved out rather than cloned.
+    fn parse_file(tokens: Vec<Spanned<Self::Token>>) -> Option<Spanned<Self::Output>> {
+        let span = match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => first.span.join(last.span),
+            _ => Span::default(),
+        };
+        let values: Vec<Self::Token> = tokens.into_iter().map(|token| token.value).collect();
+        let output = Self::parse(&values)?;
+        Some(Spanned::new(output, span))
+    }
+}
+
+/// Tokens whose kind can be inspected without their values, implemented by `derive(Lex)` through
+/// the generated `{Token}Kind` enum.
+pub trait HasKind {
+    type Kind: Copy + Eq + Hash + Debug + Display + Send + Sync + 'static;
+    fn kind(&self) -> Self::Kind;
+
+    /// The kinds of the token's own variants in fields named after them, the `{Token}Kinds`
+    /// struct, where `derive(Node)` finds the kind of a token path like `Symbol::Plus`.
+    #[doc(hidden)]
+    type Kinds;
+    #[doc(hidden)]
+    fn kinds() -> Self::Kinds;
+}
+
+/// Access to a token enum nested in a wrapper token, so nodes can match `Symbol::Plus` whatever
+/// the wrapper's variants are named. `derive(Lex)` implements it for the enum itself and for every
+/// enum one of its variants wraps, like `Symbol` in `Token::Symbol(Symbol)`.
+pub trait Project<T> {
+    fn project(&self) -> Option<&T>;
+}
+
+/// The kinds side of [`Project`], implemented by `derive(Lex)` alongside it: the kind of a token
+/// of the wrapper for the kind of the enum it wraps, like `TokenKind::Symbol(kind)` for a
+/// `SymbolKind`.
+#[doc(hidden)]
+pub trait ProjectKind<T: HasKind>: HasKind {
+    fn project_kind(kind: T::Kind) -> Self::Kind;
+}
+
+/// Formats a set of token kinds for error messages, e.g. `expected '+', '-' or identifier`.
+pub fn expected<K: Display>(kinds: &[K]) -> String {
+    let names: Vec<String> = kinds.iter().map(ToString::to_string).collect();
+    match names.split_last() {
+        None => "expected nothing".to_string(),
+        Some((last, [])) => format!("expected {last}"),
+        Some((last, rest)) => format!("expected {} or {last}", rest.join(", ")),
     }
 }
@@ -0,0 +1,188 @@
+use std::fmt::Display;
+
+use crate::Span;
+
+/// A zero-based line and column, with the column counted in characters and tabs expanded to the
+/// [`LineIndex`]'s tab width. Displays one-based as `line:column`, the way editors show it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl LineCol {
+    pub fn new(line: usize, column: usize) -> Self {
+        LineCol { line, column }
+    }
+}
+
+impl Display for LineCol {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "{}:{}", self.line + 1, self.column + 1)
+    }
+}
+
+/// A zero-based line and column in UTF-16 code units, the positions LSP clients send and expect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Utf16Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Utf16Position {
+    pub fn new(line: usize, character: usize) -> Self {
+        Utf16Position { line, character }
+    }
+}
+
+/// Converts the byte offsets in spans to line and column positions and back.
+///
+/// Lines end at `\n`, `\r\n` or a lone `\r`, and a byte order mark at the start of the text isn't
+/// counted in the first line's columns. Offsets that fall inside a character or a line break are
+/// moved back to its start, and positions past the end of a line to the line's end. The index
+/// keeps a copy of the text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineIndex {
+    text: String,
+    line_starts: Vec<usize>,
+    tab_width: usize,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let bom_len = if text.starts_with('\u{FEFF}') {
+            '\u{FEFF}'.len_utf8()
+        } else {
+            0
+        };
+        let mut line_starts = vec![bom_len];
+        let bytes = text.as_bytes();
+        for (index, &byte) in bytes.iter().enumerate() {
+            let line_break =
+                byte == b'\n' || (byte == b'\r' && bytes.get(index + 1) != Some(&b'\n'));
+            if line_break {
+                line_starts.push(index + 1);
+            }
+        }
+        LineIndex {
+            text: text.to_string(),
+            line_starts,
+            tab_width: 1,
+        }
+    }
+
+    /// Sets how many columns a tab advances to, to the next multiple of `tab_width`. Tabs are one
+    /// column by default, so columns count characters.
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width.max(1);
+        self
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The text of a line, without its line break.
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line)?;
+        Some(&self.text[start..self.line_end(line)])
+    }
+
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let (line, before) = self.line_prefix(offset);
+        let column = before
+            .chars()
+            .fold(0, |column, character| self.advance(column, character));
+        LineCol { line, column }
+    }
+
+    pub fn offset(&self, position: LineCol) -> Option<usize> {
+        let start = *self.line_starts.get(position.line)?;
+        let mut column = 0;
+        for (index, character) in self.line(position.line)?.char_indices() {
+            let next = self.advance(column, character);
+            // a column inside a tab is the tab itself
+            if next > position.column {
+                return Some(start + index);
+            }
+            column = next;
+        }
+        Some(self.line_end(position.line))
+    }
+
+    pub fn utf16(&self, offset: usize) -> Utf16Position {
+        let (line, before) = self.line_prefix(offset);
+        Utf16Position {
+            line,
+            character: before.encode_utf16().count(),
+        }
+    }
+
+    pub fn offset_utf16(&self, position: Utf16Position) -> Option<usize> {
+        let start = *self.line_starts.get(position.line)?;
+        let mut character_count = 0;
+        for (index, character) in self.line(position.line)?.char_indices() {
+            character_count += character.len_utf16();
+            // a position between the halves of a surrogate pair is the whole character
+            if character_count > position.character {
+                return Some(start + index);
+            }
+        }
+        Some(self.line_end(position.line))
+    }
+
+    /// Where a span starts and ends.
+    pub fn line_cols(&self, span: Span) -> (LineCol, LineCol) {
+        (self.line_col(span.start), self.line_col(span.end))
+    }
+
+    pub fn utf16_range(&self, span: Span) -> (Utf16Position, Utf16Position) {
+        (self.utf16(span.start), self.utf16(span.end))
+    }
+
+    /// The span between two positions, for ranges coming from an editor.
+    pub fn span(&self, start: LineCol, end: LineCol) -> Option<Span> {
+        Some(Span::new(self.offset(start)?, self.offset(end)?))
+    }
+
+    pub fn span_utf16(&self, start: Utf16Position, end: Utf16Position) -> Option<Span> {
+        Some(Span::new(
+            self.offset_utf16(start)?,
+            self.offset_utf16(end)?,
+        ))
+    }
+
+    /// The line an offset is on and the text of that line before it.
+    fn line_prefix(&self, offset: usize) -> (usize, &str) {
+        let mut offset = offset.clamp(self.line_starts[0], self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        (line, &self.text[start..offset.min(self.line_end(line))])
+    }
+
+    /// Where a line's text ends, before its line break.
+    fn line_end(&self, line: usize) -> usize {
+        let Some(&next_start) = self.line_starts.get(line + 1) else {
+            return self.text.len();
+        };
+        let with_break = &self.text[self.line_starts[line]..next_start];
+        let text = with_break.strip_suffix('\n').unwrap_or(with_break);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        self.line_starts[line] + text.len()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn advance(&self, column: usize, character: char) -> usize {
+        if character == '\t' {
+            (column / self.tab_width + 1) * self.tab_width
+        } else {
+            column + 1
+        }
+    }
+}
@@ -0,0 +1,724 @@
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::{Span, Spanned};
+
+/// Why [`Node::try_parse`](crate::Node::try_parse) failed. Positions are indices into the parsed
+/// tokens, [`ParseError::span`] turns them into spans.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub position: usize,
+    pub kind: ParseErrorKind,
+    /// The names of the tokens that could have been at `position`, like `'+'` or `integer`.
+    pub expected: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// Nothing could be parsed from the token at `position` on.
+    UnexpectedToken,
+    /// The input ended where more tokens were needed.
+    UnexpectedEnd,
+    /// The delimiter at `open` wasn't closed, its closing token was expected at `position`.
+    Unclosed { open: usize },
+}
+
+impl ParseError {
+    /// The span of the token the error is at, or an empty span after the last token when it's at
+    /// the end of the input.
+    pub fn span<T>(&self, tokens: &[Spanned<T>]) -> Span {
+        token_span(tokens, self.position)
+    }
+
+    /// For an unclosed delimiter, the span of the opening token.
+    pub fn open_span<T>(&self, tokens: &[Spanned<T>]) -> Option<Span> {
+        match self.kind {
+            ParseErrorKind::Unclosed { open } => Some(token_span(tokens, open)),
+            _ => None,
+        }
+    }
+}
+
+fn token_span<T>(tokens: &[Spanned<T>], position: usize) -> Span {
+    match (tokens.get(position), tokens.last()) {
+        (Some(token), _) => token.span,
+        (None, Some(last)) => Span::new(last.span.end, last.span.end).with_file(last.span.file),
+        (None, None) => Span::default(),
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.kind {
+            ParseErrorKind::UnexpectedToken => {
+                write!(formatter, "unexpected token at {}", self.position)?
+            }
+            ParseErrorKind::UnexpectedEnd => write!(formatter, "unexpected end of input")?,
+            ParseErrorKind::Unclosed { open } => write!(
+                formatter,
+                "unclosed delimiter at {open}, expected it to be closed at {}",
+                self.position
+            )?,
+        }
+        if !self.expected.is_empty() {
+            write!(formatter, ", {}", crate::expected(&self.expected))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A failure seen while parsing, with positions counted as tokens remaining, which is all the
+/// generated parsers know. The one with the fewest remaining got furthest into the input.
+#[derive(Debug, Clone)]
+pub(crate) struct Failure {
+    pub(crate) remaining: usize,
+    pub(crate) kind: FailureKind,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum FailureKind {
+    Unclosed { open_remaining: usize },
+    Expected(Vec<String>),
+}
+
+/// The state of one parse, from an entry point like [`Node::parse`](crate::Node::parse) until it
+/// returns. Nested parses of other tokens, like those a node written by hand collects, get their
+/// own context, so they don't mix with it.
+struct Context {
+    root: Root,
+    /// Whether failures are worth recording, only [`track`] needs them.
+    tracking: bool,
+    furthest: Option<Failure>,
+    /// Whether a sequence failed after its `#[cut]` field, until the nearest choice around it
+    /// fails in turn.
+    cut: bool,
+    /// The failures `#[recover]` enums skipped during [`recovering`], `None` outside of it.
+    recovered: Option<Vec<Recovered>>,
+    infix: InfixState,
+    /// The memo table for `#[node(memo)]` nodes, kept for parses started during [`memoize`].
+    memo: Option<Memo>,
+}
+
+impl Context {
+    fn new<T>(tokens: &[T]) -> Self {
+        Context {
+            root: Root::new(tokens),
+            tracking: false,
+            furthest: None,
+            cut: false,
+            recovered: None,
+            infix: InfixState::default(),
+            memo: MEMOIZING.with(Cell::get).map(|_| Memo::default()),
+        }
+    }
+
+    fn is_furthest(&self, remaining: usize) -> bool {
+        self.furthest
+            .as_ref()
+            .is_none_or(|current| remaining <= current.remaining)
+    }
+
+    fn record(&mut self, failure: Failure) {
+        if !self.tracking || self.cut {
+            return;
+        }
+        let Some(current) = self.furthest.as_mut() else {
+            self.furthest = Some(failure);
+            return;
+        };
+        if failure.remaining < current.remaining {
+            *current = failure;
+        } else if failure.remaining == current.remaining {
+            // expected tokens at the same position add up, but an unclosed delimiter says more,
+            // and of those the first is kept, it's from the alternative tried first
+            match (&mut current.kind, failure.kind) {
+                (FailureKind::Expected(names), FailureKind::Expected(more)) => {
+                    for name in more {
+                        if !names.contains(&name) {
+                            names.push(name);
+                        }
+                    }
+                }
+                (FailureKind::Expected(_), kind @ FailureKind::Unclosed { .. }) => {
+                    current.kind = kind
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn recovered_len(&self) -> Option<usize> {
+        self.recovered.as_ref().map(Vec::len)
+    }
+
+    /// Drops the errors recovered after the first `len`.
+    fn truncate_recovered(&mut self, len: Option<usize>) {
+        if let (Some(recovered), Some(len)) = (self.recovered.as_mut(), len) {
+            recovered.truncate(len);
+        }
+    }
+}
+
+/// Where the tokens of a context end. Generated parsers only pass on what's left of their
+/// tokens, which ends there too, so those slices are told apart by how many tokens they have left.
+/// Any other slice, like one of other tokens or a part a node written by hand cuts out, is
+/// parsed as an input of its own.
+#[derive(Clone, Copy)]
+struct Root {
+    end: *const (),
+}
+
+impl Root {
+    fn new<T>(tokens: &[T]) -> Self {
+        Root {
+            end: tokens.as_ptr_range().end.cast(),
+        }
+    }
+
+    fn contains<T>(&self, tokens: &[T]) -> bool {
+        std::ptr::eq(tokens.as_ptr_range().end.cast(), self.end)
+    }
+}
+
+/// Where in the tokens of a context a slice of them starts, as the number of tokens left.
+type Position = usize;
+
+thread_local! {
+    static CONTEXT: RefCell<Option<Context>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` on the current context, if there is one.
+fn with_context<R>(f: impl FnOnce(&mut Context) -> R) -> Option<R> {
+    CONTEXT.with(|context| context.borrow_mut().as_mut().map(f))
+}
+
+/// Makes a context current until it's closed or dropped, then puts back the one it replaced, so
+/// a panic caught during a parse doesn't leave its state behind.
+struct Scope {
+    outer: Option<Option<Context>>,
+}
+
+impl Scope {
+    fn open(context: Context) -> Self {
+        let outer = CONTEXT.with(|current| current.replace(Some(context)));
+        Scope { outer: Some(outer) }
+    }
+
+    fn close(mut self) -> Context {
+        self.restore()
+            .expect("a scope's context is current until it's closed")
+    }
+
+    fn restore(&mut self) -> Option<Context> {
+        let outer = self.outer.take()?;
+        let context = CONTEXT.with(|current| current.replace(outer))?;
+        if let Some(memo) = &context.memo {
+            MEMOIZING.with(|stats| {
+                stats.set(stats.get().map(|total| MemoStats {
+                    hits: total.hits + memo.stats.hits,
+                    misses: total.misses + memo.stats.misses,
+                }))
+            });
+        }
+        Some(context)
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+/// Runs its closure when dropped, to undo what was pushed around a parse even if it panics.
+struct Defer<F: FnMut()>(F);
+
+impl<F: FnMut()> Drop for Defer<F> {
+    fn drop(&mut self) {
+        (self.0)()
+    }
+}
+
+/// Runs `parse` in `context`, giving the context back once it's done.
+fn run<R>(context: Context, parse: impl FnOnce() -> R) -> (R, Context) {
+    let scope = Scope::open(context);
+    let result = parse();
+    (result, scope.close())
+}
+
+/// Runs `parse` as a whole parse of `tokens`, in a context of its own.
+pub(crate) fn scoped<T, R>(tokens: &[T], parse: impl FnOnce() -> R) -> R {
+    run(Context::new(tokens), parse).0
+}
+
+/// Called around the `parse_one` of `derive(Node)` parsers, so they parse in the context of the
+/// input `tokens` are part of, or in a new one for tokens of another input.
+pub fn enter<T, R>(tokens: &[T], parse: impl FnOnce() -> R) -> R {
+    let inside = CONTEXT.with(|context| {
+        context
+            .borrow()
+            .as_ref()
+            .is_some_and(|context| context.root.contains(tokens))
+    });
+    if inside {
+        parse()
+    } else {
+        scoped(tokens, parse)
+    }
+}
+
+/// Called by `derive(Node)` parsers when a delimiter opened with `open_remaining` tokens left
+/// isn't closed with `remaining` tokens left.
+pub fn unclosed(open_remaining: usize, remaining: usize) {
+    record(Failure {
+        remaining,
+        kind: FailureKind::Unclosed { open_remaining },
+    });
+}
+
+/// Called by `derive(Node)` parsers when none of the tokens named by `names` is next, with
+/// `remaining` tokens left. The names are only looked up when they're needed.
+pub fn expected(remaining: usize, names: impl FnOnce() -> Option<Vec<String>>) {
+    let wanted = with_context(|context| context.tracking && context.is_furthest(remaining));
+    if wanted != Some(true) {
+        return;
+    }
+    if let Some(names) = names().filter(|names| !names.is_empty()) {
+        record(Failure {
+            remaining,
+            kind: FailureKind::Expected(names),
+        });
+    }
+}
+
+fn record(failure: Failure) {
+    with_context(|context| context.record(failure));
+}
+
+/// Runs `parse` as a whole parse of `tokens`, returning the furthest failure recorded during it.
+pub(crate) fn track<T, R>(tokens: &[T], parse: impl FnOnce() -> R) -> (R, Option<Failure>) {
+    let context = Context {
+        tracking: true,
+        ..Context::new(tokens)
+    };
+    let (result, context) = run(context, parse);
+    (result, context.furthest)
+}
+
+/// Called by `derive(Node)` choices, like enums and optional or repeated fields, after an
+/// alternative failed. When it failed past a `#[cut]` the choice fails too, without trying the
+/// rest, and the cut ends with it: the choices around it try their alternatives again.
+pub fn take_cut() -> bool {
+    with_context(|context| std::mem::take(&mut context.cut)).unwrap_or(false)
+}
+
+/// Kept by `derive(Node)` sequences with a `#[cut]` field while they parse. Failures after the
+/// cut are kept apart from those before it, so a failing sequence reports its own failure rather
+/// than one from an alternative that got further.
+#[derive(Default)]
+pub struct Cut {
+    /// The tokens left after the cut and the furthest failure from before it, once it's passed.
+    passed: Cell<Option<(usize, Option<Failure>)>>,
+}
+
+impl Cut {
+    /// Called once the `#[cut]` field parsed, with `remaining` tokens left.
+    pub fn pass(&self, remaining: usize) {
+        let before = with_context(|context| context.furthest.take()).flatten();
+        self.passed.set(Some((remaining, before)));
+    }
+
+    /// Called with the result of the sequence, making a failure after the cut fail the nearest
+    /// choice around it.
+    pub fn finish<R>(&self, result: Option<R>) -> Option<R> {
+        let Some((remaining, before)) = self.passed.take() else {
+            return result;
+        };
+        with_context(|context| {
+            if result.is_some() {
+                if let Some(after) = std::mem::replace(&mut context.furthest, before) {
+                    context.record(after);
+                }
+            } else if !context.cut {
+                if context.furthest.is_none() {
+                    context.record(Failure {
+                        remaining,
+                        kind: FailureKind::Expected(vec![]),
+                    });
+                }
+                context.cut = true;
+            }
+        });
+        result
+    }
+}
+
+/// A region a `#[recover]` enum skipped, starting with `start` tokens left, and the furthest
+/// failure inside it.
+#[derive(Debug, Clone)]
+pub(crate) struct Recovered {
+    start: usize,
+    failure: Option<Failure>,
+}
+
+/// Where parsing resumes around a sync token of a `#[recover]` enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// Before the token, which is left for the parent, like a closing brace.
+    Before,
+    /// After the token, which is skipped along with the rest, like a statement terminator.
+    After,
+}
+
+/// Runs `parse` as a whole parse of `tokens` with `#[recover]` enums recovering from failures,
+/// giving what it parsed and the errors from every region they skipped, followed by the error
+/// `try_parse` would give if it didn't parse everything.
+pub(crate) fn recovering<'t, N, T>(
+    tokens: &'t [T],
+    parse: impl FnOnce() -> Option<(N, &'t [T])>,
+) -> (Option<N>, Vec<ParseError>) {
+    let context = Context {
+        tracking: true,
+        recovered: Some(vec![]),
+        ..Context::new(tokens)
+    };
+    let (result, context) = run(context, parse);
+
+    let len = tokens.len();
+    let mut errors: Vec<ParseError> = context
+        .recovered
+        .unwrap_or_default()
+        .into_iter()
+        .map(|recovered| error(len, Some(recovered.start), recovered.failure))
+        .collect();
+    let stopped = result.as_ref().map(|(_, rest)| rest.len());
+    if stopped != Some(0) {
+        errors.push(error(len, stopped, context.furthest));
+    }
+    errors.sort_by_key(|error| error.position);
+    (result.map(|(output, _)| output), errors)
+}
+
+/// Called by `derive(Node)` `#[peek]` and `#[not]` fields to check whether `parse` succeeds
+/// without keeping anything from it: its failures, recovered errors and cut are all dropped.
+pub fn lookahead<R>(parse: impl FnOnce() -> Option<R>) -> bool {
+    let saved = with_context(|context| {
+        (
+            context.furthest.clone(),
+            context.recovered_len(),
+            context.cut,
+        )
+    });
+    let matched = parse().is_some();
+    if let Some((furthest, recovered, cut)) = saved {
+        with_context(|context| {
+            context.furthest = furthest;
+            context.truncate_recovered(recovered);
+            context.cut = cut;
+        });
+    }
+    matched
+}
+
+/// Called by `derive(Node)` sequences around their parse, so errors recovered inside one that
+/// fails as a whole are dropped with it.
+pub fn attempt<R>(parse: impl FnOnce() -> Option<R>) -> Option<R> {
+    let before = with_context(|context| context.recovered_len()).flatten();
+    let result = parse();
+    if result.is_none() {
+        with_context(|context| context.truncate_recovered(before));
+    }
+    result
+}
+
+/// Called by the `parse_one` of `#[recover]` enums. When `parse` fails during [`recovering`], the
+/// tokens up to the next sync token `resume` finds are skipped and given to `error` for the
+/// enum's `#[error]` variant. Without a token to skip it fails after all.
+pub fn recover<'t, N, T: Clone>(
+    tokens: &'t [T],
+    resume: impl Fn(&T) -> Option<Resume>,
+    error: impl FnOnce(Vec<T>) -> N,
+    parse: impl FnOnce() -> Option<(N, &'t [T])>,
+) -> Option<(N, &'t [T])> {
+    let recovering = with_context(|context| context.recovered.is_some());
+    if recovering != Some(true) {
+        return parse();
+    }
+    let outer = with_context(|context| context.furthest.take()).flatten();
+    let result = parse();
+    let failure = with_context(|context| std::mem::replace(&mut context.furthest, outer)).flatten();
+    if result.is_some() {
+        if let Some(failure) = failure {
+            record(failure);
+        }
+        return result;
+    }
+
+    let mut end = 0;
+    for token in tokens {
+        match resume(token) {
+            Some(Resume::Before) => break,
+            Some(Resume::After) => {
+                end += 1;
+                break;
+            }
+            None => end += 1,
+        }
+    }
+    if end == 0 {
+        if let Some(failure) = failure {
+            record(failure);
+        }
+        return None;
+    }
+    with_context(|context| {
+        if let Some(recovered) = context.recovered.as_mut() {
+            recovered.push(Recovered {
+                start: tokens.len(),
+                failure,
+            });
+        }
+    });
+    Some((error(tokens[..end].to_vec()), &tokens[end..]))
+}
+
+/// The error for a parse of `len` tokens that stopped with `stopped` tokens left, or didn't parse
+/// anything when it's `None`, given the furthest failure recorded.
+pub(crate) fn error(len: usize, stopped: Option<usize>, failure: Option<Failure>) -> ParseError {
+    let stopped = stopped.unwrap_or(len);
+    let position = |remaining: usize| len - remaining;
+    let unexpected = |remaining: usize| match remaining {
+        0 => ParseErrorKind::UnexpectedEnd,
+        _ => ParseErrorKind::UnexpectedToken,
+    };
+    match failure {
+        Some(failure) if failure.remaining <= stopped => {
+            let (kind, expected) = match failure.kind {
+                FailureKind::Unclosed { open_remaining } => (
+                    ParseErrorKind::Unclosed {
+                        open: position(open_remaining),
+                    },
+                    vec![],
+                ),
+                FailureKind::Expected(names) => (unexpected(failure.remaining), names),
+            };
+            ParseError {
+                position: position(failure.remaining),
+                kind,
+                expected,
+            }
+        }
+        _ => ParseError {
+            position: position(stopped),
+            kind: unexpected(stopped),
+            expected: vec![],
+        },
+    }
+}
+
+/// What `#[infix]` enums are in the middle of parsing. Parsing at a position where one of them
+/// is blocked or needs a minimum precedence gives a different result than parsing there fresh.
+#[derive(Default)]
+struct InfixState {
+    /// Left operands being parsed, where their enum mustn't recurse into itself.
+    operand_starts: Vec<(TypeId, Position)>,
+    /// Right operands being parsed, which only take operators binding at least this tightly.
+    min_precedences: Vec<(TypeId, Position, u32)>,
+}
+
+/// Called by `derive(Node)` `#[infix]` enums, identified by `rule`, to check whether they are parsing a
+/// left operand at `tokens` already.
+pub fn is_operand_start<T>(rule: TypeId, tokens: &[T]) -> bool {
+    with_context(|context| {
+        let start = tokens.len();
+        context.infix.operand_starts.contains(&(rule, start))
+    })
+    .unwrap_or(false)
+}
+
+/// Called by `derive(Node)` `#[infix]` enums for the lowest precedence of operator they can take
+/// at `tokens`.
+pub fn min_precedence<T>(rule: TypeId, tokens: &[T]) -> u32 {
+    with_context(|context| {
+        let start = tokens.len();
+        context
+            .infix
+            .min_precedences
+            .iter()
+            .rev()
+            .find(|(other, at, _)| *other == rule && *at == start)
+            .map_or(0, |(_, _, precedence)| *precedence)
+    })
+    .unwrap_or(0)
+}
+
+/// Called by `derive(Node)` `#[infix]` enums to parse their left operand at `tokens`.
+pub fn parse_operand<T, R>(rule: TypeId, tokens: &[T], parse: impl FnOnce() -> R) -> R {
+    with_context(|context| {
+        let start = tokens.len();
+        context.infix.operand_starts.push((rule, start));
+    });
+    let _pop = Defer(|| {
+        with_context(|context| context.infix.operand_starts.pop());
+    });
+    parse()
+}
+
+/// Called by `derive(Node)` `#[infix]` enums to parse a right operand at `tokens`, taking only
+/// operators of at least `precedence`.
+pub fn parse_right_operand<T, R>(
+    rule: TypeId,
+    tokens: &[T],
+    precedence: u32,
+    parse: impl FnOnce() -> R,
+) -> R {
+    with_context(|context| {
+        let start = tokens.len();
+        context
+            .infix
+            .min_precedences
+            .push((rule, start, precedence));
+    });
+    let _pop = Defer(|| {
+        with_context(|context| context.infix.min_precedences.pop());
+    });
+    parse()
+}
+
+/// How often `#[node(memo)]` nodes were found in the memo table during [`memoize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoStats {
+    /// Parses answered from the table.
+    pub hits: usize,
+    /// Parses that ran and were added to it.
+    pub misses: usize,
+}
+
+/// A node's rule, the position it was parsed at, and the `#[infix]` state there.
+type MemoKey = (TypeId, Position, Vec<(TypeId, Option<u32>)>);
+
+/// What parsing a node gave: the node and how many tokens it took, with the errors recovered
+/// while parsing it.
+type MemoValue<N> = (Option<(N, usize)>, Vec<Recovered>);
+
+/// The memo table of a parse, made during [`memoize`]. Each entry holds the `MemoValue` of the
+/// node's output type.
+#[derive(Default)]
+struct Memo {
+    table: HashMap<MemoKey, Box<dyn Any>>,
+    stats: MemoStats,
+}
+
+thread_local! {
+    /// The stats of the parses that ended during [`memoize`], `None` outside of it.
+    static MEMOIZING: Cell<Option<MemoStats>> = const { Cell::new(None) };
+}
+
+/// Runs `parse` with a memo table for `#[node(memo)]` nodes in each parse it starts, so each of
+/// them is parsed at most once per position of its input, and returns how well the tables did.
+/// Each table is dropped with its parse.
+///
+/// ```
+/// use rcket::{Lex, Node};
+///
+/// #[derive(Lex, Debug, PartialEq, Clone)]
+/// enum Token {
+///     #[token("(")]
+///     Open,
+///     #[token(")")]
+///     Close,
+/// }
+///
+/// #[derive(Node, Debug, PartialEq, Clone)]
+/// #[node(memo)]
+/// enum Program {
+///     Nested(#[token(Token::Open)] (), Box<Program>, #[token(Token::Close)] ()),
+///     Empty,
+/// }
+///
+/// let tokens = Token::lex("(())");
+/// let (program, stats) = rcket::parse::memoize(|| Program::parse(&tokens));
+/// assert!(program.is_some());
+/// println!("{} hits, {} misses", stats.hits, stats.misses);
+/// ```
+pub fn memoize<R>(parse: impl FnOnce() -> R) -> (R, MemoStats) {
+    let outer = MEMOIZING.with(|stats| stats.replace(Some(MemoStats::default())));
+    let _restore = Defer(|| MEMOIZING.with(|stats| stats.set(outer)));
+    let result = parse();
+    (result, MEMOIZING.with(Cell::get).unwrap_or_default())
+}
+
+/// Called by the `parse_one` of `#[node(memo)]` nodes, identified by `rule`, looking the result
+/// of parsing `tokens` up in the memo table of the current parse before running `parse`. Outside
+/// of [`memoize`] it just runs `parse`.
+///
+/// The table holds on to the node, so it has to be `'static`, and a hit clones it, which copies
+/// `Box`ed subtrees all the way down, so a node with large subtrees is cheaper to memoize with
+/// them behind an `Rc`.
+pub fn memoized<'t, N: Clone + 'static, T>(
+    rule: TypeId,
+    tokens: &'t [T],
+    parse: impl FnOnce() -> Option<(N, &'t [T])>,
+) -> Option<(N, &'t [T])> {
+    let key = with_context(|context| {
+        context.memo.as_ref()?;
+        let infix = &context.infix;
+        let at = tokens.len();
+        let blocked = infix
+            .operand_starts
+            .iter()
+            .filter(|(_, operand_start)| *operand_start == at)
+            .map(|(rule, _)| (*rule, None));
+        let precedences = infix
+            .min_precedences
+            .iter()
+            .filter(|(_, operand_start, _)| *operand_start == at)
+            .map(|(rule, _, precedence)| (*rule, Some(*precedence)));
+        Some((rule, at, blocked.chain(precedences).collect()))
+    })
+    .flatten();
+    let Some(key) = key else {
+        return parse();
+    };
+
+    let cached = with_context(|context| {
+        let memo = context.memo.as_mut()?;
+        let (result, errors) = memo.table.get(&key)?.downcast_ref::<MemoValue<N>>()?;
+        memo.stats.hits += 1;
+        Some((result.clone(), errors.clone()))
+    })
+    .flatten();
+    if let Some((result, errors)) = cached {
+        with_context(|context| {
+            if let Some(recovered) = context.recovered.as_mut() {
+                recovered.extend(errors);
+            }
+        });
+        return result.map(|(node, consumed)| (node, &tokens[consumed..]));
+    }
+
+    let before = with_context(|context| context.recovered_len()).flatten();
+    let result = parse();
+    with_context(|context| {
+        // a failure past a `#[cut]` fails the choice around the node, it isn't the node's result
+        if context.cut {
+            return;
+        }
+        let errors = match (context.recovered.as_ref(), before) {
+            (Some(recovered), Some(before)) => recovered[before..].to_vec(),
+            _ => vec![],
+        };
+        if let Some(memo) = context.memo.as_mut() {
+            let entry = result
+                .as_ref()
+                .map(|(node, rest)| (node.clone(), tokens.len() - rest.len()));
+            memo.table.insert(key, Box::new((entry, errors)));
+            memo.stats.misses += 1;
+        }
+    });
+    result
+}
@@ -0,0 +1,207 @@
+use crate::{Span, Spanned};
+
+/// A transformation over lexed tokens that runs before parsing, such as inserting semicolons or
+/// expanding macros.
+///
+/// Passes take the whole token list so they can insert, delete and rewrite tokens. Tokens they
+/// create should get a span from the input: an inserted token an empty span where it was
+/// inserted, and a token built from others the span joining them. Closures over
+/// `Vec<Spanned<T>>` are passes too, and passes compose with [`TokenPass::then`].
+pub trait TokenPass<T> {
+    fn run(&mut self, tokens: Vec<Spanned<T>>) -> Vec<Spanned<T>>;
+
+    /// Runs `self`, then `next` on its output.
+    fn then<P: TokenPass<T>>(self, next: P) -> Then<Self, P>
+    where
+        Self: Sized,
+    {
+        Then { first: self, next }
+    }
+}
+
+impl<T, F: FnMut(Vec<Spanned<T>>) -> Vec<Spanned<T>>> TokenPass<T> for F {
+    fn run(&mut self, tokens: Vec<Spanned<T>>) -> Vec<Spanned<T>> {
+        self(tokens)
+    }
+}
+
+/// Two passes run one after the other, built by [`TokenPass::then`].
+pub struct Then<A, B> {
+    first: A,
+    next: B,
+}
+
+impl<T, A: TokenPass<T>, B: TokenPass<T>> TokenPass<T> for Then<A, B> {
+    fn run(&mut self, tokens: Vec<Spanned<T>>) -> Vec<Spanned<T>> {
+        let tokens = self.first.run(tokens);
+        self.next.run(tokens)
+    }
+}
+
+/// Deletes the tokens a predicate matches, like comments the grammar doesn't mention.
+pub struct Remove<F> {
+    predicate: F,
+}
+
+impl<F> Remove<F> {
+    pub fn new<T>(predicate: F) -> Self
+    where
+        F: FnMut(&T) -> bool,
+    {
+        Remove { predicate }
+    }
+}
+
+impl<T, F: FnMut(&T) -> bool> TokenPass<T> for Remove<F> {
+    fn run(&mut self, mut tokens: Vec<Spanned<T>>) -> Vec<Spanned<T>> {
+        tokens.retain(|token| !(self.predicate)(&token.value));
+        tokens
+    }
+}
+
+/// Automatic semicolon insertion in the style of Go: a semicolon is inserted after the last token
+/// of a line when that token can end a statement, like an identifier, a literal or `)`.
+///
+/// Line breaks aren't tokens, so the pass looks for them in the source text between spans. The
+/// inserted semicolon gets an empty span right after the token it follows.
+pub struct InsertSemicolons<'src, T, F> {
+    source: &'src str,
+    semicolon: T,
+    ends_statement: F,
+}
+
+impl<'src, T, F: FnMut(&T) -> bool> InsertSemicolons<'src, T, F> {
+    pub fn new(source: &'src str, semicolon: T, ends_statement: F) -> Self {
+        InsertSemicolons {
+            source,
+            semicolon,
+            ends_statement,
+        }
+    }
+}
+
+impl<T: Clone, F: FnMut(&T) -> bool> TokenPass<T> for InsertSemicolons<'_, T, F> {
+    fn run(&mut self, tokens: Vec<Spanned<T>>) -> Vec<Spanned<T>> {
+        let mut output = Vec::with_capacity(tokens.len());
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            let (end, file) = (token.span.end, token.span.file);
+            let next_start = tokens
+                .peek()
+                .map_or(self.source.len(), |next| next.span.start);
+            let line_ends = tokens.peek().is_none()
+                || self
+                    .source
+                    .get(end..next_start)
+                    .is_some_and(|between| between.contains('\n'));
+            let insert = line_ends && (self.ends_statement)(&token.value);
+            output.push(token);
+            if insert {
+                output.push(Spanned::new(
+                    self.semicolon.clone(),
+                    Span::new(end, end).with_file(file),
+                ));
+            }
+        }
+        output
+    }
+}
+
+/// Joins two adjacent tokens, with no whitespace between them, into one when `join` returns a
+/// token for the pair, e.g. `>` `>` into `>>`. The joined token can join again, so `>>` `=`
+/// becomes `>>=` as well, and its span covers all of them.
+///
+/// Lexing `>` on its own and joining in a pass keeps `Vec<Vec<i32>>` lexable as two closing
+/// brackets, while the pass decides where a shift is meant.
+pub struct JoinAdjacent<F> {
+    join: F,
+}
+
+impl<F> JoinAdjacent<F> {
+    pub fn new<T>(join: F) -> Self
+    where
+        F: FnMut(&T, &T) -> Option<T>,
+    {
+        JoinAdjacent { join }
+    }
+}
+
+impl<T, F: FnMut(&T, &T) -> Option<T>> TokenPass<T> for JoinAdjacent<F> {
+    fn run(&mut self, tokens: Vec<Spanned<T>>) -> Vec<Spanned<T>> {
+        let mut output: Vec<Spanned<T>> = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            if let Some(previous) = output.last_mut()
+                && previous.span.end == token.span.start
+                && let Some(joined) = (self.join)(&previous.value, &token.value)
+            {
+                previous.value = joined;
+                previous.span = previous.span.join(token.span);
+                continue;
+            }
+            output.push(token);
+        }
+        output
+    }
+}
+
+/// Object-like macro expansion in the style of the C preprocessor: every token equal to a macro's
+/// name is replaced by its body.
+///
+/// Bodies are expanded again, except for macros already being expanded, so a macro that refers to
+/// itself stops instead of looping. Expanded tokens take the span of the name they replaced.
+pub struct ExpandMacros<T> {
+    macros: Vec<(T, Vec<T>)>,
+}
+
+impl<T> ExpandMacros<T> {
+    pub fn new() -> Self {
+        ExpandMacros { macros: Vec::new() }
+    }
+
+    /// Defines a macro, replacing any earlier definition of the same name.
+    pub fn define(mut self, name: T, body: Vec<T>) -> Self
+    where
+        T: PartialEq,
+    {
+        self.macros.retain(|(existing, _)| *existing != name);
+        self.macros.push((name, body));
+        self
+    }
+}
+
+impl<T> Default for ExpandMacros<T> {
+    fn default() -> Self {
+        ExpandMacros::new()
+    }
+}
+
+impl<T: Clone + PartialEq> ExpandMacros<T> {
+    fn expand(&self, token: T, span: Span, active: &mut Vec<usize>, output: &mut Vec<Spanned<T>>) {
+        let definition = self
+            .macros
+            .iter()
+            .position(|(name, _)| *name == token)
+            .filter(|index| !active.contains(index));
+        match definition {
+            Some(index) => {
+                active.push(index);
+                for body_token in &self.macros[index].1 {
+                    self.expand(body_token.clone(), span, active, output);
+                }
+                active.pop();
+            }
+            None => output.push(Spanned::new(token, span)),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> TokenPass<T> for ExpandMacros<T> {
+    fn run(&mut self, tokens: Vec<Spanned<T>>) -> Vec<Spanned<T>> {
+        let mut output = Vec::with_capacity(tokens.len());
+        let mut active = Vec::new();
+        for token in tokens {
+            self.expand(token.value, token.span, &mut active, &mut output);
+        }
+        output
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt::Display;
+
+use crate::{Lex, LexError, Lexed, LineCol, LineIndex, Span};
+
+/// Identifies a file in a [`SourceMap`], carried by every [`Span`]. Text lexed without a file is
+/// in [`FileId::NONE`], the default, which no map gives out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileId(pub u32);
+
+impl FileId {
+    /// The file of spans from input that wasn't added to a [`SourceMap`].
+    pub const NONE: FileId = FileId(u32::MAX);
+}
+
+impl Default for FileId {
+    fn default() -> Self {
+        FileId::NONE
+    }
+}
+
+/// The files of one parse session, such as a program and everything it imports.
+///
+/// Lexing a file through [`SourceMap::lex`] puts its [`FileId`] in every span, and
+/// [`SourceMap::locate`] turns any span back into a file name and line. Passing a `FileId` from
+/// another map panics.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+#[derive(Debug, Clone)]
+struct SourceFile {
+    name: String,
+    lines: LineIndex,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    /// Adds a file and returns its id. Names are only used in messages, so they don't have to be
+    /// unique.
+    pub fn add(&mut self, name: impl Into<String>, text: &str) -> FileId {
+        let file = FileId(self.files.len() as u32);
+        assert_ne!(file, FileId::NONE, "too many files in the source map");
+        self.files.push(SourceFile {
+            name: name.into(),
+            lines: LineIndex::new(text),
+        });
+        file
+    }
+
+    pub fn name(&self, file: FileId) -> &str {
+        &self.file(file).name
+    }
+
+    pub fn text(&self, file: FileId) -> &str {
+        self.file(file).lines.text()
+    }
+
+    pub fn line_index(&self, file: FileId) -> &LineIndex {
+        &self.file(file).lines
+    }
+
+    /// Finds a file by name, the first one if several share it.
+    pub fn find(&self, name: &str) -> Option<FileId> {
+        self.files
+            .iter()
+            .position(|file| file.name == name)
+            .map(|index| FileId(index as u32))
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = FileId> + '_ {
+        (0..self.files.len()).map(|index| FileId(index as u32))
+    }
+
+    /// Lexes a file, with every token and error span in it.
+    pub fn lex<'src, T: Lex<'src>>(&'src self, file: FileId) -> Lexed<T> {
+        T::lex_file(file, self.text(file))
+    }
+
+    /// The file name and lines and columns of a span.
+    pub fn locate(&self, span: Span) -> Location<'_> {
+        let (start, end) = self.line_index(span.file).line_cols(span);
+        Location {
+            name: self.name(span.file),
+            start,
+            end,
+        }
+    }
+
+    /// Formats a lex error with its location, like `main.frg:3:14: unrecognized input "@"`.
+    pub fn describe(&self, error: &LexError) -> String {
+        format!(
+            "{}: {} {:?}",
+            self.locate(error.span),
+            error.message,
+            error.text
+        )
+    }
+
+    fn file(&self, file: FileId) -> &SourceFile {
+        &self.files[file.0 as usize]
+    }
+}
+
+/// Where a span is, resolved by [`SourceMap::locate`]. Displays as `name:line:column` of its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location<'a> {
+    pub name: &'a str,
+    pub start: LineCol,
+    pub end: LineCol,
+}
+
+impl Display for Location<'_> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "{}:{}", self.name, self.start)
+    }
+}
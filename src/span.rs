@@ -0,0 +1,54 @@
+use crate::FileId;
+
+/// A byte range in the lexed input, and the file it's in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub file: FileId,
+}
+
+impl Span {
+    /// A span in no file, which is what single-file lexing produces.
+    pub fn new(start: usize, end: usize) -> Self {
+        Span {
+            start,
+            end,
+            file: FileId::NONE,
+        }
+    }
+
+    pub fn with_file(self, file: FileId) -> Span {
+        Span { file, ..self }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The smallest span covering both `self` and `other`, which should be in the same file.
+    pub fn join(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+            file: self.file,
+        }
+    }
+}
+
+/// A value together with where it came from in the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(value: T, span: Span) -> Self {
+        Spanned { value, span }
+    }
+}
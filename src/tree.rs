@@ -0,0 +1,225 @@
+use std::fmt::Display;
+
+use crate::{HasKind, Span, Spanned};
+
+/// Tokens that open or close a group, implemented by `derive(Lex)` from the enum's
+/// `#[lex(delimiters(LeftParen = RightParen, ...))]` pairs. Variants wrapping another lexer use
+/// its pairs.
+pub trait Delimiters: HasKind {
+    /// For an opening delimiter, the kind of token that closes it.
+    fn closing_kind(&self) -> Option<Self::Kind>;
+
+    fn is_closing(&self) -> bool;
+}
+
+/// A token, or a group of token trees between a pair of delimiters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenTree<T> {
+    Token(Spanned<T>),
+    Group(Group<T>),
+}
+
+impl<T> TokenTree<T> {
+    pub fn span(&self) -> Span {
+        match self {
+            TokenTree::Token(token) => token.span,
+            TokenTree::Group(group) => group.span(),
+        }
+    }
+
+    /// The tokens of the trees in order, delimiters included, ready for `Node::parse`.
+    pub fn flatten(trees: Vec<TokenTree<T>>) -> Vec<Spanned<T>> {
+        let mut tokens = Vec::new();
+        for tree in trees {
+            tree.flatten_into(&mut tokens);
+        }
+        tokens
+    }
+
+    fn flatten_into(self, tokens: &mut Vec<Spanned<T>>) {
+        match self {
+            TokenTree::Token(token) => tokens.push(token),
+            TokenTree::Group(group) => {
+                tokens.push(group.open);
+                for tree in group.trees {
+                    tree.flatten_into(tokens);
+                }
+                tokens.extend(group.close);
+            }
+        }
+    }
+}
+
+/// Token trees between an opening delimiter and its closing one, which is missing if the group
+/// was never closed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group<T> {
+    pub open: Spanned<T>,
+    pub trees: Vec<TokenTree<T>>,
+    pub close: Option<Spanned<T>>,
+}
+
+impl<T> Group<T> {
+    /// From the opening delimiter to the closing one, or to the last tree inside if unclosed.
+    pub fn span(&self) -> Span {
+        let end = match (&self.close, self.trees.last()) {
+            (Some(close), _) => close.span,
+            (None, Some(last)) => last.span(),
+            (None, None) => self.open.span,
+        };
+        self.open.span.join(end)
+    }
+}
+
+/// A delimiter without a partner.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DelimiterError {
+    /// `open` is never closed, the input ends at `end`.
+    Unclosed { open: Span, end: Span },
+    /// `open` is closed by the wrong kind of delimiter at `close`.
+    Mismatched { open: Span, close: Span },
+    /// `close` doesn't close any group.
+    Unopened { close: Span },
+}
+
+impl Display for DelimiterError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DelimiterError::Unclosed { open, end } => write!(
+                formatter,
+                "unclosed delimiter at {}..{}, input ends at {}",
+                open.start, open.end, end.end
+            ),
+            DelimiterError::Mismatched { open, close } => write!(
+                formatter,
+                "delimiter at {}..{} doesn't match the one opened at {}..{}",
+                close.start, close.end, open.start, open.end
+            ),
+            DelimiterError::Unopened { close } => write!(
+                formatter,
+                "unexpected closing delimiter at {}..{}",
+                close.start, close.end
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DelimiterError {}
+
+/// Token trees of a whole input, plus the delimiters that didn't pair up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenTrees<T> {
+    pub trees: Vec<TokenTree<T>>,
+    pub errors: Vec<DelimiterError>,
+}
+
+/// A group still waiting for its closing delimiter.
+struct OpenGroup<T: HasKind> {
+    open: Spanned<T>,
+    closing_kind: T::Kind,
+    trees: Vec<TokenTree<T>>,
+}
+
+impl<T: Delimiters> TokenTrees<T> {
+    /// Groups tokens between their delimiters.
+    ///
+    /// A closing delimiter that matches an outer group closes every group inside it, reporting
+    /// each as mismatched. One that matches no open group closes the innermost one as mismatched,
+    /// so `(]` is a single error, and is only unopened, staying a plain token, outside of every
+    /// group. Groups still open at the end are reported as unclosed.
+    pub fn new(tokens: Vec<Spanned<T>>) -> Self {
+        let end = tokens.last().map_or(Span::default(), |token| {
+            Span::new(token.span.end, token.span.end).with_file(token.span.file)
+        });
+        let mut errors = Vec::new();
+        let mut trees = Vec::new();
+        let mut stack: Vec<OpenGroup<T>> = Vec::new();
+
+        for token in tokens {
+            if let Some(closing_kind) = token.value.closing_kind() {
+                stack.push(OpenGroup {
+                    open: token,
+                    closing_kind,
+                    trees: Vec::new(),
+                });
+                continue;
+            }
+            if !token.value.is_closing() {
+                push_tree(&mut stack, &mut trees, TokenTree::Token(token));
+                continue;
+            }
+
+            let kind = token.value.kind();
+            let Some(depth) = stack.iter().rposition(|group| group.closing_kind == kind) else {
+                let Some(group) = stack.pop() else {
+                    errors.push(DelimiterError::Unopened { close: token.span });
+                    push_tree(&mut stack, &mut trees, TokenTree::Token(token));
+                    continue;
+                };
+                errors.push(DelimiterError::Mismatched {
+                    open: group.open.span,
+                    close: token.span,
+                });
+                let tree = TokenTree::Group(Group {
+                    open: group.open,
+                    trees: group.trees,
+                    close: Some(token),
+                });
+                push_tree(&mut stack, &mut trees, tree);
+                continue;
+            };
+            while stack.len() > depth + 1 {
+                let group = stack.pop().expect("stack is deeper than depth");
+                errors.push(DelimiterError::Mismatched {
+                    open: group.open.span,
+                    close: token.span,
+                });
+                let tree = TokenTree::Group(Group {
+                    open: group.open,
+                    trees: group.trees,
+                    close: None,
+                });
+                push_tree(&mut stack, &mut trees, tree);
+            }
+            let group = stack.pop().expect("matching group is on the stack");
+            let tree = TokenTree::Group(Group {
+                open: group.open,
+                trees: group.trees,
+                close: Some(token),
+            });
+            push_tree(&mut stack, &mut trees, tree);
+        }
+
+        while let Some(group) = stack.pop() {
+            errors.push(DelimiterError::Unclosed {
+                open: group.open.span,
+                end,
+            });
+            let tree = TokenTree::Group(Group {
+                open: group.open,
+                trees: group.trees,
+                close: None,
+            });
+            push_tree(&mut stack, &mut trees, tree);
+        }
+        // unclosed groups are found innermost first, report them in input order
+        errors.sort_by_key(|error| match error {
+            DelimiterError::Unclosed { open, .. } | DelimiterError::Mismatched { open, .. } => {
+                open.start
+            }
+            DelimiterError::Unopened { close } => close.start,
+        });
+        TokenTrees { trees, errors }
+    }
+}
+
+fn push_tree<T: HasKind>(
+    stack: &mut [OpenGroup<T>],
+    trees: &mut Vec<TokenTree<T>>,
+    tree: TokenTree<T>,
+) {
+    match stack.last_mut() {
+        Some(group) => group.trees.push(tree),
+        None => trees.push(tree),
+    }
+}
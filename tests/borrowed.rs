@@ -0,0 +1,79 @@
+use rcket::{Lex, Node};
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Symbol {
+    #[token("=")]
+    Equals,
+    #[token(";")]
+    Semicolon,
+}
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Literal<'a> {
+    #[regex(r"\d+")]
+    Int(i64),
+    #[seq(token("\""), regex(r#"[^"]*"#), token("\""))]
+    String(&'a str),
+    #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*")]
+    Identifier(&'a str),
+}
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Token<'a> {
+    Symbol(Symbol),
+    Literal(Literal<'a>),
+}
+
+#[derive(Node, Debug, PartialEq)]
+enum Value<'a> {
+    #[extract(Literal::Int)]
+    Int(i64),
+    #[extract(Literal::String)]
+    String(&'a str),
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct Assignment<'a>(
+    #[extract(Literal::Identifier)] &'a str,
+    #[token(Symbol::Equals)] (),
+    Value<'a>,
+    #[token(Symbol::Semicolon)] (),
+);
+
+#[test]
+fn lex_borrows_from_input() {
+    let input = String::from(r#"name = "rcket";"#);
+    let tokens = Token::lex(&input);
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Literal(Literal::Identifier("name")),
+            Token::Symbol(Symbol::Equals),
+            Token::Literal(Literal::String("rcket")),
+            Token::Symbol(Symbol::Semicolon),
+        ]
+    );
+
+    let Token::Literal(Literal::Identifier(name)) = tokens[0] else {
+        panic!("expected an identifier");
+    };
+    assert!(input.as_bytes().as_ptr_range().contains(&name.as_ptr()));
+}
+
+#[test]
+fn parse_borrowed_tokens() {
+    let input = "total = 42;";
+    let tokens = Token::lex(input);
+    let assignment = Assignment::parse(&tokens).unwrap();
+    assert_eq!(assignment, Assignment("total", (), Value::Int(42), ()));
+    assert!(
+        input
+            .as_bytes()
+            .as_ptr_range()
+            .contains(&assignment.0.as_ptr())
+    );
+    assert_eq!(
+        assignment.to_string(),
+        "Assignment (total Value (Int (42)))"
+    );
+}
@@ -0,0 +1,252 @@
+use rcket::lex::builtin;
+use rcket::{InvalidToken, Lex, LexError, Span};
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Token<'a> {
+    #[token("/")]
+    Slash,
+    #[token("-")]
+    Minus,
+    #[token("..")]
+    Range,
+    #[token(".")]
+    Period,
+    #[token("let")]
+    Let,
+    #[matcher(builtin::line_comment)]
+    #[matcher(builtin::nested_block_comment)]
+    #[lex(priority = 2)]
+    Comment,
+    #[matcher(builtin::float)]
+    Float(f64),
+    #[matcher(builtin::int)]
+    Int(i64),
+    #[matcher(builtin::rust_string)]
+    String(String),
+    #[matcher(builtin::rust_char)]
+    Char(char),
+    #[matcher(builtin::identifier)]
+    Identifier(&'a str),
+}
+
+fn invalid(len: usize, message: &str) -> InvalidToken {
+    InvalidToken {
+        len,
+        message: message.to_string(),
+    }
+}
+
+#[test]
+fn ints() {
+    assert_eq!(builtin::int("1_000 "), Ok(Some((1000, 5))));
+    assert_eq!(builtin::int("0xFF_ff"), Ok(Some((0xFFFF, 7))));
+    assert_eq!(builtin::int("0o17"), Ok(Some((0o17, 4))));
+    assert_eq!(builtin::int("0b1010..2"), Ok(Some((0b1010, 6))));
+    assert_eq!(builtin::int("_1"), Ok(None));
+    assert_eq!(
+        builtin::int("0b102 "),
+        Err(invalid(5, "invalid digit '2' in base 2 literal"))
+    );
+    assert_eq!(
+        builtin::int("0x_"),
+        Err(invalid(3, "expected digits after \"0x\""))
+    );
+    assert_eq!(
+        builtin::int("340282366920938463463374607431768211456"),
+        Err(invalid(39, "integer literal is too large"))
+    );
+}
+
+#[test]
+fn floats() {
+    assert_eq!(builtin::float("1.5"), Ok(Some((1.5, 3))));
+    assert_eq!(builtin::float("1_000.25e-2"), Ok(Some((10.0025, 11))));
+    assert_eq!(builtin::float("1E3"), Ok(Some((1000.0, 3))));
+    assert_eq!(builtin::float("1e400"), Ok(Some((f64::INFINITY, 5))));
+    assert_eq!(builtin::float("12"), Ok(None));
+    assert_eq!(builtin::float("1..2"), Ok(None));
+    assert_eq!(builtin::float("1.max(2)"), Ok(None));
+    assert_eq!(
+        builtin::float("2.5e+"),
+        Err(invalid(5, "expected at least one digit in the exponent"))
+    );
+    assert_eq!(
+        builtin::float("1.5px"),
+        Err(invalid(5, "invalid suffix \"px\" on float literal"))
+    );
+}
+
+#[test]
+fn strings() {
+    assert_eq!(
+        builtin::rust_string(r#""a\t\"b\" \u{e9}\x41" rest"#),
+        Ok(Some(("a\t\"b\" éA".to_string(), 21)))
+    );
+    assert_eq!(
+        builtin::rust_string("\"one \\\n    two\""),
+        Ok(Some(("one two".to_string(), 15)))
+    );
+    assert_eq!(
+        builtin::rust_string(r#""\q" x"#),
+        Err(invalid(4, "unknown escape \\q"))
+    );
+    assert_eq!(
+        builtin::rust_string(r#""\x80""#),
+        Err(invalid(6, "hexadecimal escape must be at most \\x7F"))
+    );
+    assert_eq!(
+        builtin::rust_string("\"open"),
+        Err(invalid(5, "unterminated \" literal"))
+    );
+    assert_eq!(
+        builtin::c_string(r#""\101\x42\a\?""#),
+        Ok(Some(("AB\x07?".to_string(), 14)))
+    );
+    assert_eq!(
+        builtin::c_string("\"line\nnext\""),
+        Err(invalid(5, "unterminated \" literal"))
+    );
+    assert_eq!(
+        builtin::rust_raw_string(r##"r#"say "hi""# x"##),
+        Ok(Some((r#"say "hi""#, 13)))
+    );
+    assert_eq!(builtin::rust_raw_string("r#type"), Ok(None));
+}
+
+#[test]
+fn escapes_end_at_the_closing_quote() {
+    assert_eq!(
+        builtin::rust_string(r#""\x4" x"#),
+        Err(invalid(5, "hexadecimal escape must have two digits"))
+    );
+    assert_eq!(
+        builtin::rust_string(r#""\x" x"#),
+        Err(invalid(4, "hexadecimal escape must have two digits"))
+    );
+    assert_eq!(
+        builtin::rust_string(r#""\u{41" x }"#),
+        Err(invalid(7, "unterminated unicode escape"))
+    );
+    assert_eq!(
+        builtin::rust_char(r"'\u{41' }'"),
+        Err(invalid(7, "unterminated unicode escape"))
+    );
+    assert_eq!(
+        builtin::c_string(r#""\x" x"#),
+        Err(invalid(4, "expected hexadecimal digits after \\x"))
+    );
+}
+
+#[test]
+fn escapes_out_of_range() {
+    assert_eq!(
+        builtin::c_string(r#""\377""#),
+        Ok(Some(("\u{FF}".to_string(), 6)))
+    );
+    assert_eq!(
+        builtin::c_string(r#""\400""#),
+        Err(invalid(6, "octal escape must be at most \\377"))
+    );
+    assert_eq!(
+        builtin::c_string(r#""\xFF""#),
+        Ok(Some(("\u{FF}".to_string(), 6)))
+    );
+    assert_eq!(
+        builtin::c_string(r#""\x100""#),
+        Err(invalid(7, "hexadecimal escape must be at most \\xFF"))
+    );
+    assert_eq!(
+        builtin::rust_string(r#""\u{110000}""#),
+        Err(invalid(12, "invalid unicode escape"))
+    );
+}
+
+#[test]
+fn chars() {
+    assert_eq!(builtin::rust_char(r"'\''"), Ok(Some(('\'', 4))));
+    assert_eq!(builtin::rust_char(r"'\u{1F600}'"), Ok(Some(('😀', 11))));
+    assert_eq!(builtin::rust_char("'a: loop"), Ok(None));
+    assert_eq!(builtin::c_char(r"'\0'"), Ok(Some(('\0', 4))));
+    assert_eq!(
+        builtin::c_char("''"),
+        Err(invalid(2, "empty character literal"))
+    );
+    assert_eq!(
+        builtin::c_char("'ab'"),
+        Err(invalid(4, "character literal must contain one character"))
+    );
+}
+
+#[test]
+fn identifiers() {
+    assert_eq!(builtin::identifier("größe = 1"), Ok(Some(("größe", 7))));
+    assert_eq!(builtin::identifier("変数"), Ok(Some(("変数", 6))));
+    assert_eq!(builtin::identifier("_tmp1+"), Ok(Some(("_tmp1", 5))));
+    assert_eq!(builtin::identifier("1st"), Ok(None));
+}
+
+#[test]
+fn comments() {
+    assert_eq!(
+        builtin::line_comment("// note\r\nx"),
+        Ok(Some((" note", 8)))
+    );
+    assert_eq!(
+        builtin::block_comment("/* a /* b */ c */"),
+        Ok(Some((" a /* b ", 12)))
+    );
+    assert_eq!(
+        builtin::nested_block_comment("/* a /* b */ c */"),
+        Ok(Some((" a /* b */ c ", 17)))
+    );
+    assert_eq!(
+        builtin::nested_block_comment("/* a /* b */"),
+        Err(invalid(12, "unterminated block comment"))
+    );
+}
+
+#[test]
+fn lex_with_builtins() {
+    assert_eq!(
+        Token::lex("let größe 0x1F / 2.5e1 // half\n'a' \"b\\n\" /* c */ 1..2"),
+        vec![
+            Token::Let,
+            Token::Identifier("größe"),
+            Token::Int(0x1F),
+            Token::Slash,
+            Token::Float(25.0),
+            Token::Comment,
+            Token::Char('a'),
+            Token::String("b\n".to_string()),
+            Token::Comment,
+            Token::Int(1),
+            Token::Range,
+            Token::Int(2),
+        ]
+    );
+}
+
+#[test]
+fn builtin_errors_are_lex_errors() {
+    let lexed = Token::lex_spanned("x = 99999999999999999999 \"open");
+    assert_eq!(
+        lexed.errors,
+        vec![
+            LexError {
+                span: Span::new(2, 3),
+                text: "=".to_string(),
+                message: "unrecognized input".to_string(),
+            },
+            LexError {
+                span: Span::new(4, 24),
+                text: "99999999999999999999".to_string(),
+                message: "out of range integral type conversion attempted".to_string(),
+            },
+            LexError {
+                span: Span::new(25, 30),
+                text: "\"open".to_string(),
+                message: "unterminated \" literal".to_string(),
+            },
+        ]
+    );
+}
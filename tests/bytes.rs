@@ -0,0 +1,73 @@
+use rcket::Lex;
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+#[lex(bytes, check)]
+enum Frame<'a> {
+    #[token("GET")]
+    Get,
+    #[token(":")]
+    Colon,
+    #[regex(r"\d+")]
+    Length(usize),
+    #[regex(r"[a-z]+")]
+    Word(Vec<u8>),
+    #[seq(token("<"), regex(r"(?-u)[^>]*"), token(">"))]
+    Payload(&'a [u8]),
+    #[regex(r"(?-u)[\x80-\xFF]+")]
+    Latin1(&'a [u8]),
+}
+
+#[test]
+fn lex_bytes() {
+    let input: &[u8] = b"GET path:12 <\x00\xFFraw>";
+    assert_eq!(
+        Frame::lex(input),
+        vec![
+            Frame::Get,
+            Frame::Word(b"path".to_vec()),
+            Frame::Colon,
+            Frame::Length(12),
+            Frame::Payload(b"\x00\xFFraw"),
+        ]
+    );
+}
+
+#[test]
+fn lex_invalid_utf8() {
+    // "café" in Latin-1, the é is a lone 0xE9 byte
+    let input: &[u8] = b"caf\xE9 GETx";
+    assert_eq!(
+        Frame::lex(input),
+        vec![
+            Frame::Word(b"caf".to_vec()),
+            Frame::Latin1(b"\xE9"),
+            Frame::Word(b"x".to_vec()),
+        ]
+    );
+}
+
+/// Byte matchers of the crate's own, in a module that happens to share the name of
+/// `rcket::lex::builtin`.
+mod builtin {
+    use rcket::InvalidToken;
+
+    pub fn ones(input: &[u8]) -> Result<Option<(usize, usize)>, InvalidToken> {
+        let len = input.iter().take_while(|byte| **byte == 1).count();
+        Ok((len > 0).then_some((len, len)))
+    }
+}
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+#[lex(bytes)]
+enum Ones {
+    #[matcher(builtin::ones)]
+    Run(usize),
+}
+
+#[test]
+fn lex_with_own_builtin_module() {
+    assert_eq!(
+        Ones::lex(b"\x01\x01 \x01"),
+        vec![Ones::Run(2), Ones::Run(1)]
+    );
+}
@@ -0,0 +1,125 @@
+use rcket::{Lex, Node, ParseErrorKind};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::{Keyword, Literal, Symbol, Token};
+
+#[derive(Node, Debug, PartialEq)]
+enum Value {
+    #[extract(Literal::Int)]
+    Int(i32),
+    #[extract(Literal::Identifier)]
+    Name(String),
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct Block(
+    #[token(Symbol::LeftBrace)] (),
+    Vec<Statement>,
+    #[token(Symbol::RightBrace)] (),
+);
+
+#[derive(Node, Debug, PartialEq)]
+struct If {
+    #[token(Keyword::If)]
+    #[cut]
+    keyword: (),
+    condition: Value,
+    body: Block,
+}
+
+/// `if` followed by anything, which an `if` with a broken body shouldn't end up as.
+#[derive(Node, Debug, PartialEq)]
+struct Words(#[token(Keyword::If)] (), #[many1] Vec<Value>);
+
+#[derive(Node, Debug, PartialEq)]
+enum Statement {
+    If(If),
+    Words(Words),
+    Value(Value),
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct UncutIf(#[token(Keyword::If)] (), Value, Block);
+
+#[derive(Node, Debug, PartialEq)]
+enum UncutStatement {
+    If(UncutIf),
+    Words(Words),
+    Value(Value),
+}
+
+/// A statement or, when that fails, words, even those starting with `if`.
+#[derive(Node, Debug, PartialEq)]
+enum Line {
+    Statement(Statement),
+    Words(Words),
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct Program(Vec<Statement>);
+
+fn name(name: &str) -> Value {
+    Value::Name(name.to_string())
+}
+
+#[test]
+fn cut_commits_to_variant() {
+    let tokens = Token::lex("if x 5");
+    assert_eq!(Statement::parse(&tokens), None);
+    assert_eq!(
+        UncutStatement::parse(&tokens),
+        Some(UncutStatement::Words(Words(
+            (),
+            vec![name("x"), Value::Int(5)]
+        )))
+    );
+    assert_eq!(
+        Statement::parse(&Token::lex("if x { 5 }")),
+        Some(Statement::If(If {
+            keyword: (),
+            condition: name("x"),
+            body: Block((), vec![Statement::Value(Value::Int(5))], ()),
+        }))
+    );
+    // the next parse starts over
+    assert_eq!(
+        Statement::parse(&Token::lex("x")),
+        Some(Statement::Value(name("x")))
+    );
+}
+
+#[test]
+fn cut_errors_point_inside() {
+    let error = Statement::try_parse(&Token::lex("if x 5")).unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedToken);
+    assert_eq!(error.position, 2);
+    assert_eq!(error.expected, vec!["'{'".to_string()]);
+
+    // a repetition stops before a statement that failed past its cut, the error stays inside it
+    let error = Program::try_parse(&Token::lex("x if y { z if }")).unwrap_err();
+    assert_eq!(error.position, 6);
+    assert_eq!(
+        error.expected,
+        vec!["integer".to_string(), "identifier".to_string()]
+    );
+    assert_eq!(Program::parse(&Token::lex("x if y")), None);
+    assert_eq!(
+        Program::parse(&Token::lex("x if y { }")).map(|program| program.0.len()),
+        Some(2)
+    );
+}
+
+#[test]
+fn cut_ends_at_nearest_choice() {
+    let tokens = Token::lex("if x 5");
+    // `Statement` doesn't try `Words` after its `If` failed, but `Line` around it does
+    assert_eq!(
+        Line::parse(&tokens),
+        Some(Line::Words(Words((), vec![name("x"), Value::Int(5)])))
+    );
+    assert_eq!(
+        Line::parse(&Token::lex("x")),
+        Some(Line::Statement(Statement::Value(name("x"))))
+    );
+}
@@ -0,0 +1,128 @@
+use rcket::{Lex, Node, ParseError, ParseErrorKind, Span};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::{Literal, Symbol, Token};
+
+#[derive(Node, Debug, PartialEq)]
+#[delimited(Symbol::LeftParen, Symbol::RightParen)]
+enum Expression {
+    #[extract(Literal::Int)]
+    Int(i32),
+    Call(
+        #[extract(Literal::Identifier)] String,
+        #[delimited(Symbol::LeftParen, Symbol::RightParen)]
+        #[sep(Symbol::Comma)]
+        Vec<Expression>,
+    ),
+    #[delimited(Symbol::LeftBracket, Symbol::RightBracket)]
+    List(#[sep(Symbol::Comma)] Vec<Expression>),
+    BinaryOperation(BinaryOperation),
+}
+
+#[derive(Node, Debug, PartialEq)]
+enum BinaryOperation {
+    #[infix(Symbol::Plus)]
+    #[prec(1)]
+    Addition(Box<Expression>, Box<Expression>),
+    #[infix(Symbol::Minus)]
+    #[prec(1)]
+    Subtraction(Box<Expression>, Box<Expression>),
+    #[infix(Symbol::Star)]
+    #[prec(2)]
+    Multiplication(Box<Expression>, Box<Expression>),
+}
+
+fn int(value: i32) -> Box<Expression> {
+    Box::new(Expression::Int(value))
+}
+
+fn operation(operation: BinaryOperation) -> Box<Expression> {
+    Box::new(Expression::BinaryOperation(operation))
+}
+
+#[test]
+fn parenthesized_operations() {
+    let expression = Expression::parse(&Token::lex("5 + (2 - 4) * 7")).unwrap();
+    assert_eq!(
+        expression,
+        *operation(BinaryOperation::Addition(
+            int(5),
+            operation(BinaryOperation::Multiplication(
+                operation(BinaryOperation::Subtraction(int(2), int(4))),
+                int(7),
+            )),
+        ))
+    );
+    assert_eq!(
+        Expression::parse(&Token::lex("((1))")).unwrap(),
+        Expression::Int(1)
+    );
+    assert_eq!(
+        Expression::parse(&Token::lex("(1 + 2) - 3")).unwrap(),
+        Expression::parse(&Token::lex("1 + 2 - 3")).unwrap()
+    );
+}
+
+#[test]
+fn delimited_fields_and_variants() {
+    assert_eq!(
+        Expression::parse(&Token::lex("max(1, (2))")).unwrap(),
+        Expression::Call(
+            "max".to_string(),
+            vec![Expression::Int(1), Expression::Int(2)]
+        )
+    );
+    assert_eq!(
+        Expression::parse(&Token::lex("[1, 2 + 3]")).unwrap(),
+        Expression::List(vec![
+            Expression::Int(1),
+            *operation(BinaryOperation::Addition(int(2), int(3))),
+        ])
+    );
+    assert_eq!(
+        Expression::parse(&Token::lex("[]")).unwrap(),
+        Expression::List(vec![])
+    );
+}
+
+#[test]
+fn unclosed_delimiter() {
+    let lexed = Token::lex_spanned("5 + (2 - 4 * 7");
+    let tokens = lexed.clone().into_values();
+    let error = Expression::try_parse(&tokens).unwrap_err();
+    assert_eq!(
+        error,
+        ParseError {
+            position: 8,
+            kind: ParseErrorKind::Unclosed { open: 2 },
+            expected: vec![],
+        }
+    );
+    assert_eq!(error.open_span(&lexed.tokens), Some(Span::new(4, 5)));
+    assert_eq!(error.span(&lexed.tokens), Span::new(14, 14));
+
+    let error = Expression::try_parse(&Token::lex("[1, 2")).unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::Unclosed { open: 0 });
+    assert_eq!(error.position, 4);
+}
+
+#[test]
+fn unexpected_tokens() {
+    assert_eq!(
+        Expression::try_parse(&Token::lex("1 + 2 3")),
+        Err(ParseError {
+            position: 3,
+            kind: ParseErrorKind::UnexpectedToken,
+            expected: vec!["'+'".to_string(), "'-'".to_string(), "'*'".to_string()],
+        })
+    );
+    assert_eq!(
+        Expression::try_parse(&[]).unwrap_err().kind,
+        ParseErrorKind::UnexpectedEnd
+    );
+    assert_eq!(
+        Expression::try_parse(&Token::lex("(1 + 2)")).unwrap(),
+        *operation(BinaryOperation::Addition(int(1), int(2)))
+    );
+}
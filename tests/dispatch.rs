@@ -0,0 +1,163 @@
+use std::fmt::Display;
+
+use rcket::{Lex, Node, ParseErrorKind};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::{Keyword, Literal, Symbol, Token};
+
+#[path = "fixtures/counting_name.rs"]
+mod counting_name;
+use counting_name::{NAME_PARSES, Name};
+
+#[derive(Node, Debug, PartialEq)]
+enum VarType {
+    #[token(Keyword::Int)]
+    Int,
+    #[token(Keyword::Str)]
+    Str,
+}
+
+#[derive(Node, Debug, PartialEq)]
+enum Value {
+    #[extract(Literal::Int)]
+    Int(i32),
+    #[extract(Literal::String)]
+    String(String),
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct Declaration(VarType, Name, #[token(Symbol::Equals)] (), Value);
+
+#[derive(Node, Debug, PartialEq)]
+struct Assignment(Name, #[token(Symbol::Equals)] (), Value);
+
+#[derive(Node, Debug, PartialEq)]
+struct Return(#[token(Keyword::Return)] (), Option<Value>);
+
+#[derive(Node, Debug, PartialEq)]
+struct Shout(#[token(Symbol::Exclamation)] Option<()>, Name);
+
+#[derive(Node, Debug, PartialEq)]
+enum Statement {
+    Declaration(Declaration),
+    Assignment(Assignment),
+    Return(Return),
+    Shout(Shout),
+}
+
+fn names(names: &[&str]) -> Option<Vec<String>> {
+    Some(names.iter().map(ToString::to_string).collect())
+}
+
+#[test]
+fn first_sets() {
+    assert_eq!(VarType::first(), names(&["'int'", "'str'"]));
+    assert_eq!(Value::first(), names(&["integer", "string literal"]));
+    assert_eq!(Declaration::first(), names(&["'int'", "'str'"]));
+    // `Name` is written by hand, and only tells the kinds of its tokens
+    assert_eq!(Name::first(), None);
+    assert_eq!(Shout::first(), names(&["'!'", "identifier"]));
+    assert_eq!(
+        Statement::first(),
+        names(&["'int'", "'str'", "identifier", "'return'", "'!'"])
+    );
+
+    let tokens = Token::lex("return str x !");
+    assert!(Return::starts_with(&tokens[0]));
+    assert!(!Declaration::starts_with(&tokens[0]));
+    assert!(Declaration::starts_with(&tokens[1]));
+    assert!(!Shout::starts_with(&tokens[0]));
+    assert!(Shout::starts_with(&tokens[2]));
+    assert!(Shout::starts_with(&tokens[3]));
+    assert!(Statement::starts_with(&tokens[3]));
+}
+
+#[test]
+fn only_matching_variants_are_tried() {
+    NAME_PARSES.with(|parses| parses.set(0));
+    let statement = Statement::parse(&Token::lex("return 5")).unwrap();
+    assert_eq!(
+        statement,
+        Statement::Return(Return((), Some(Value::Int(5))))
+    );
+    // `Assignment` is skipped instead of failing on its `Name`
+    assert_eq!(NAME_PARSES.with(|parses| parses.get()), 0);
+
+    NAME_PARSES.with(|parses| parses.set(0));
+    let statement = Statement::parse(&Token::lex("x = 5")).unwrap();
+    assert_eq!(
+        statement,
+        Statement::Assignment(Assignment(Name("x".to_string()), (), Value::Int(5)))
+    );
+    assert_eq!(NAME_PARSES.with(|parses| parses.get()), 1);
+
+    assert_eq!(
+        Statement::parse(&Token::lex("!x")).unwrap(),
+        Statement::Shout(Shout(Some(()), Name("x".to_string())))
+    );
+}
+
+#[test]
+fn errors_list_expected_tokens() {
+    let error = Declaration::try_parse(&Token::lex("int x =")).unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedEnd);
+    assert_eq!(error.position, 3);
+    assert_eq!(
+        error.expected,
+        names(&["integer", "string literal"]).unwrap()
+    );
+    assert_eq!(
+        error.to_string(),
+        "unexpected end of input, expected integer or string literal"
+    );
+
+    let error = Declaration::try_parse(&Token::lex("float x = 5")).unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedToken);
+    assert_eq!(error.position, 0);
+    assert_eq!(error.expected, names(&["'int'", "'str'"]).unwrap());
+
+    let error = Declaration::try_parse(&Token::lex("int x 5")).unwrap_err();
+    assert_eq!(error.position, 2);
+    assert_eq!(error.to_string(), "unexpected token at 2, expected '='");
+}
+
+/// A string literal holding values, which are lexed and parsed from its text on their own.
+#[derive(Debug, PartialEq)]
+struct Quoted(Values);
+
+#[derive(Node, Debug, PartialEq)]
+struct Values(Vec<Value>);
+
+impl Node for Quoted {
+    type Token = Token;
+    type Output = Self;
+
+    fn parse_one(tokens: &[Token]) -> Option<(Self, &[Token])> {
+        match tokens.split_first() {
+            Some((Token::Literal(Literal::String(text)), rest)) => {
+                let values = Values::parse(&Token::lex(text.trim_matches('"')))?;
+                Some((Quoted(values), rest))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for Quoted {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "\"{}\"", self.0)
+    }
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct Constant(Name, #[token(Symbol::Equals)] (), Quoted);
+
+#[test]
+fn nested_parses_keep_their_failures() {
+    // the nested parse expected another value at the end of its own tokens, which isn't where
+    // the outer parse stopped
+    let error = Constant::try_parse(&Token::lex(r#"x = "1 2" 5"#)).unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedToken);
+    assert_eq!(error.position, 3);
+}
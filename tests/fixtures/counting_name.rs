@@ -0,0 +1,42 @@
+// shared by the tests including it with `#[path]`, which also include `frg_lexer_types.rs`
+
+use std::cell::Cell;
+use std::fmt::Display;
+
+use rcket::Node;
+
+use crate::frg_lexer_types::{Literal, LiteralKind, Token, TokenKind};
+
+thread_local! {
+    pub static NAME_PARSES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// An identifier that counts how often it's parsed. It tells the kind of token it starts with, so
+/// enums can skip it like a derived node.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Name(pub String);
+
+impl Node for Name {
+    type Token = Token;
+    type Output = Self;
+
+    fn first_kinds() -> Option<Vec<TokenKind>> {
+        Some(vec![TokenKind::Literal(LiteralKind::Identifier)])
+    }
+
+    fn parse_one(tokens: &[Token]) -> Option<(Self, &[Token])> {
+        NAME_PARSES.with(|parses| parses.set(parses.get() + 1));
+        match tokens.split_first() {
+            Some((Token::Literal(Literal::Identifier(name)), rest)) => {
+                Some((Name(name.clone()), rest))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for Name {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "{}", self.0)
+    }
+}
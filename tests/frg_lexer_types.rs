@@ -1,6 +1,7 @@
 use rcket::Lex;
 
 #[derive(Lex, Debug, PartialEq, Clone)]
+#[lex(check)]
 pub enum Keyword {
     #[token("struct")]
     Struct,
@@ -26,7 +27,7 @@ pub enum Keyword {
     Map,
     #[token("set")]
     Set,
-    #[regex(r"r?e?t?u?r?n?")]
+    #[token("return")]
     Return,
     #[token("if")]
     If,
@@ -37,6 +38,10 @@ pub enum Keyword {
 }
 
 #[derive(Lex, Debug, PartialEq, Clone)]
+#[lex(
+    check,
+    delimiters(LeftBrace = RightBrace, LeftParen = RightParen, LeftBracket = RightBracket)
+)]
 pub enum Symbol {
     #[token("{")]
     LeftBrace,
@@ -93,20 +98,26 @@ pub enum Symbol {
 }
 
 #[derive(Lex, Debug, PartialEq, Clone)]
+#[lex(check)]
 pub enum Literal {
     #[regex(r"\d+\.\d+")]
     Float(f32),
     #[regex(r"\d+")]
+    #[lex(name = "integer")]
     Int(i32),
     #[seq(token("\""), regex(r#"[^"]*"#), token("\""))]
+    #[lex(name = "string literal")]
     String(String),
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*")]
     Identifier(String),
 }
 
 #[derive(Lex, Debug, PartialEq, Clone)]
+#[lex(check)]
 pub enum Token {
     Keyword(Keyword),
     Symbol(Symbol),
     Literal(Literal),
+    #[error]
+    Error(String),
 }
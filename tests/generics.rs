@@ -0,0 +1,125 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use rcket::{Lex, Node};
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Number<N: FromStr> {
+    #[regex(r"\d+")]
+    Value(N),
+}
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Token<'a, N>
+where
+    N: FromStr,
+{
+    #[token(",")]
+    Comma,
+    #[token(";")]
+    Semicolon,
+    #[regex("[a-z]+")]
+    Word(&'a str),
+    Number(Number<N>),
+}
+
+#[derive(Node, Debug, PartialEq, Clone)]
+#[node(token = Token<'a, u32>)]
+enum Atom<'a> {
+    #[extract(Token::Word)]
+    Word(&'a str),
+    #[extract(Number::Value)]
+    Number(u32),
+}
+
+#[derive(Node, Debug, PartialEq)]
+#[node(token = Token<'a, u32>)]
+struct Pair<'a, T, S> {
+    first: T,
+    separator: S,
+    second: T,
+    _input: std::marker::PhantomData<&'a ()>,
+}
+
+#[derive(Node, Debug, PartialEq)]
+#[node(token = Token<'a, u32>)]
+enum Either<'a, L, R: Display> {
+    Left(L),
+    Right(R),
+    Neither(
+        #[token(Token::Semicolon)] (),
+        std::marker::PhantomData<&'a ()>,
+    ),
+}
+
+#[derive(Node, Debug, PartialEq)]
+#[node(token = Token<'a, u32>)]
+struct List<'a, T>
+where
+    T: Clone,
+{
+    #[sep(Token::Comma)]
+    items: Vec<T>,
+    #[token(Token::Semicolon)]
+    _end: (),
+    _input: std::marker::PhantomData<&'a ()>,
+}
+
+#[test]
+fn generic_tokens() {
+    let tokens = Token::<u8>::lex("a, 7");
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Word("a"),
+            Token::Comma,
+            Token::Number(Number::Value(7))
+        ]
+    );
+    assert_eq!(tokens[2].kind(), TokenKind::Number(NumberKind::Value));
+    assert_eq!(Token::<u8>::lex_spanned("300").errors.len(), 1);
+}
+
+#[derive(Node, Debug, PartialEq)]
+#[node(token = Token<'a, u32>)]
+struct Comma<'a>(#[token(Token::Comma)] (), std::marker::PhantomData<&'a ()>);
+
+#[test]
+fn generic_nodes() {
+    let tokens = Token::<u32>::lex("a, 7");
+    let pair = Pair::<Atom, Comma>::parse(&tokens).unwrap();
+    assert_eq!(pair.first, Atom::Word("a"));
+    assert_eq!(pair.second, Atom::Number(7));
+    assert_eq!(
+        pair.to_string(),
+        "Pair (Atom (Word (a)) Comma () Atom (Number (7)))"
+    );
+
+    let tokens = Token::<u32>::lex("a, 7, b;");
+    let list = List::<Atom>::parse(&tokens).unwrap();
+    assert_eq!(
+        list.items,
+        vec![Atom::Word("a"), Atom::Number(7), Atom::Word("b")]
+    );
+}
+
+#[test]
+fn generic_enum() {
+    type WordOrPair<'a> = Either<'a, Pair<'a, Atom<'a>, Comma<'a>>, Atom<'a>>;
+    let tokens = Token::<u32>::lex("a, b");
+    assert!(matches!(
+        WordOrPair::parse(&tokens).unwrap(),
+        Either::Left(Pair { .. })
+    ));
+    let tokens = Token::<u32>::lex("a");
+    assert_eq!(
+        WordOrPair::parse(&tokens).unwrap(),
+        Either::Right(Atom::Word("a"))
+    );
+    assert_eq!(
+        WordOrPair::parse(&Token::<u32>::lex(";"))
+            .unwrap()
+            .to_string(),
+        "Either (Neither ())"
+    );
+}
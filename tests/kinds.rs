@@ -0,0 +1,51 @@
+use rcket::{HasKind, Lex, expected};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::{KeywordKind, LiteralKind, SymbolKind, Token, TokenKind};
+
+#[test]
+fn token_kinds() {
+    let kinds: Vec<TokenKind> = Token::lex(r#"int x = "hi""#)
+        .iter()
+        .map(Token::kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            TokenKind::Keyword(KeywordKind::Int),
+            TokenKind::Literal(LiteralKind::Identifier),
+            TokenKind::Symbol(SymbolKind::Equals),
+            TokenKind::Literal(LiteralKind::String),
+        ]
+    );
+}
+
+#[test]
+fn kind_names() {
+    assert_eq!(SymbolKind::PlusEquals.name(), "'+='");
+    assert_eq!(KeywordKind::Map.name(), "'map'");
+    assert_eq!(LiteralKind::Identifier.name(), "identifier");
+    assert_eq!(LiteralKind::Int.name(), "integer");
+    assert_eq!(
+        TokenKind::Literal(LiteralKind::String).to_string(),
+        "string literal"
+    );
+}
+
+#[test]
+fn expected_message() {
+    let (token, _) = Token::lex_one("+").unwrap();
+    assert_eq!(
+        expected(&[HasKind::kind(&token), TokenKind::Symbol(SymbolKind::Minus)]),
+        "expected '+' or '-'"
+    );
+    assert_eq!(
+        expected(&[
+            TokenKind::Symbol(SymbolKind::Plus),
+            TokenKind::Symbol(SymbolKind::Minus),
+            TokenKind::Literal(LiteralKind::Identifier),
+        ]),
+        "expected '+', '-' or identifier"
+    );
+}
@@ -1,8 +1,10 @@
-use rcket::Lex;
+use std::cell::Cell;
+
+use rcket::{InvalidToken, Lex};
 
 #[path = "frg_lexer_types.rs"]
 mod frg_lexer_types;
-use frg_lexer_types::{Keyword, Literal, Symbol, Token};
+use frg_lexer_types::{Keyword, KeywordKind, Literal, LiteralKind, Symbol, Token};
 
 #[test]
 fn lex_float() {
@@ -61,3 +63,256 @@ fn lex_full() {
         ]
     );
 }
+
+#[test]
+fn lex_return() {
+    let tokens = Token::lex("return returned");
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Keyword(Keyword::Return),
+            Token::Literal(Literal::Identifier("returned".into())),
+        ]
+    );
+}
+
+#[test]
+fn lex_error_runs() {
+    let tokens = Token::lex("x = @#$ 5 ~");
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Literal(Literal::Identifier("x".into())),
+            Token::Symbol(Symbol::Equals),
+            Token::Error("@#$".into()),
+            Token::Literal(Literal::Int(5)),
+            Token::Error("~".into()),
+        ]
+    );
+}
+
+#[test]
+fn lex_error_stops_at_token() {
+    let tokens = Token::lex("x@@=1");
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Literal(Literal::Identifier("x".into())),
+            Token::Error("@@".into()),
+            Token::Symbol(Symbol::Equals),
+            Token::Literal(Literal::Int(1)),
+        ]
+    );
+}
+
+#[test]
+fn lex_error_run_spans_whitespace() {
+    let lexed = Token::lex_spanned("x @@ @@  ~ = 1");
+    assert_eq!(lexed.errors.len(), 1);
+    assert_eq!(
+        lexed.errors[0].to_string(),
+        r#""@@ @@  ~" at 2..10: unrecognized input"#
+    );
+    assert_eq!(
+        lexed.into_values(),
+        vec![
+            Token::Literal(Literal::Identifier("x".into())),
+            Token::Error("@@ @@  ~".into()),
+            Token::Symbol(Symbol::Equals),
+            Token::Literal(Literal::Int(1)),
+        ]
+    );
+    // the whitespace after the run isn't part of it
+    assert_eq!(
+        Token::lex("@@ @@ ").last(),
+        Some(&Token::Error("@@ @@".into()))
+    );
+}
+
+#[test]
+fn lex_unterminated_string() {
+    let tokens = Token::lex(r#"str s = "never closed"#);
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Keyword(Keyword::Str),
+            Token::Literal(Literal::Identifier("s".into())),
+            Token::Symbol(Symbol::Equals),
+            Token::Error(r#""never closed"#.into()),
+        ]
+    );
+}
+
+#[test]
+fn lex_spans() {
+    let lexed = Token::lex_spanned("int x = 42");
+    let spans: Vec<(usize, usize)> = lexed
+        .tokens
+        .iter()
+        .map(|token| (token.span.start, token.span.end))
+        .collect();
+    assert_eq!(spans, vec![(0, 3), (4, 5), (6, 7), (8, 10)]);
+    assert!(lexed.errors.is_empty());
+}
+
+#[test]
+fn lex_overflowing_int() {
+    let lexed = Token::lex_spanned("x = 99999999999 + 1");
+    assert_eq!(lexed.errors.len(), 1);
+    let error = &lexed.errors[0];
+    assert_eq!((error.span.start, error.span.end), (4, 15));
+    assert_eq!(error.text, "99999999999");
+    assert_eq!(error.message, "number too large to fit in target type");
+
+    // the digits become an error token instead of being lexed as something else
+    let tokens: Vec<Token> = lexed.tokens.into_iter().map(|token| token.value).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Literal(Literal::Identifier("x".into())),
+            Token::Symbol(Symbol::Equals),
+            Token::Error("99999999999".into()),
+            Token::Symbol(Symbol::Plus),
+            Token::Literal(Literal::Int(1)),
+        ]
+    );
+}
+
+#[test]
+fn lex_reports_unrecognized_input() {
+    let lexed = Token::lex_spanned("x @@");
+    assert_eq!(lexed.errors.len(), 1);
+    assert_eq!(
+        lexed.errors[0].to_string(),
+        r#""@@" at 2..4: unrecognized input"#
+    );
+}
+
+thread_local! {
+    static DIGIT_ATTEMPTS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A single digit, lexed by hand to count how often lexing is attempted.
+struct Digit;
+
+impl<'src> Lex<'src> for Digit {
+    fn try_lex_after(
+        _previous: Option<&'src str>,
+        input: &'src str,
+    ) -> Result<Option<(Self, &'src str)>, InvalidToken> {
+        DIGIT_ATTEMPTS.with(|attempts| attempts.set(attempts.get() + 1));
+        Ok(input
+            .strip_prefix(|character: char| character.is_ascii_digit())
+            .map(|rest| (Digit, rest)))
+    }
+
+    fn lex_one(input: &'src str) -> Option<(Self, &'src str)> {
+        Self::try_lex_one(input).ok().flatten()
+    }
+}
+
+#[test]
+fn lex_error_run_is_linear() {
+    DIGIT_ATTEMPTS.with(|attempts| attempts.set(0));
+    let input = format!("{}1", "@ ".repeat(1000));
+    let lexed = Digit::lex_spanned(&input);
+    assert_eq!(lexed.errors.len(), 1);
+    assert_eq!(lexed.errors[0].span.end, 1999);
+    assert_eq!(lexed.tokens.len(), 1);
+    // one attempt per unlexable character and one for the digit
+    assert_eq!(DIGIT_ATTEMPTS.with(|attempts| attempts.get()), 1001);
+}
+
+/// A lexer written against `lex_one` alone, which can't report conversion failures.
+#[derive(Debug, PartialEq)]
+struct Word<'src>(&'src str);
+
+impl<'src> Lex<'src> for Word<'src> {
+    fn lex_one(input: &'src str) -> Option<(Self, &'src str)> {
+        let end = input
+            .find(|character: char| !character.is_alphabetic())
+            .unwrap_or(input.len());
+        (end > 0).then(|| (Word(&input[..end]), &input[end..]))
+    }
+}
+
+#[test]
+fn lex_one_impls_still_lex() {
+    assert_eq!(Word::lex("ab cd"), vec![Word("ab"), Word("cd")]);
+    let lexed = Word::lex_spanned("ab 1");
+    assert_eq!(lexed.tokens.len(), 1);
+    assert_eq!(lexed.errors[0].text, "1");
+}
+
+/// Matches nothing, but says it matched.
+fn empty(_input: &str) -> Result<Option<(u8, usize)>, InvalidToken> {
+    Ok(Some((0, 0)))
+}
+
+#[derive(Lex, Debug, PartialEq)]
+enum Letter {
+    #[matcher(empty)]
+    Empty(u8),
+    #[token("a")]
+    A,
+}
+
+/// Fails on every character without taking any of it.
+struct Invalid;
+
+impl<'src> Lex<'src> for Invalid {
+    fn try_lex_after(
+        _previous: Option<&'src str>,
+        _input: &'src str,
+    ) -> Result<Option<(Self, &'src str)>, InvalidToken> {
+        Err(InvalidToken {
+            len: 0,
+            message: "invalid".to_string(),
+        })
+    }
+
+    fn lex_one(input: &'src str) -> Option<(Self, &'src str)> {
+        Self::try_lex_one(input).ok().flatten()
+    }
+}
+
+#[test]
+fn lex_empty_matches_advance() {
+    let lexed = Letter::lex_spanned("a b a");
+    assert_eq!(
+        lexed
+            .tokens
+            .into_iter()
+            .map(|token| token.value)
+            .collect::<Vec<_>>(),
+        vec![Letter::A, Letter::A]
+    );
+    assert_eq!(lexed.errors.len(), 1);
+    assert_eq!(lexed.errors[0].text, "b");
+
+    let lexed = Invalid::lex_spanned("xy");
+    let texts: Vec<&str> = lexed
+        .errors
+        .iter()
+        .map(|error| error.text.as_str())
+        .collect();
+    assert_eq!(texts, vec!["x", "y"]);
+}
+
+#[derive(Lex, Debug, PartialEq)]
+enum Shadowed {
+    Literal(Literal),
+    Keyword(Keyword),
+}
+
+#[test]
+fn wrapped_patterns_are_checked() {
+    let conflicts = rcket::lex::check::conflicts(&Shadowed::patterns());
+    // every keyword is lexed as an identifier
+    assert_eq!(conflicts.len(), 20);
+    assert!(conflicts.contains(
+        &"pattern \"struct\" of `Keyword::Struct` is unreachable, it is always lexed as `Literal::Identifier`"
+            .to_string()
+    ));
+    assert!(rcket::lex::check::conflicts(&Token::patterns()).is_empty());
+}
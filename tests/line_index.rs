@@ -0,0 +1,86 @@
+use rcket::{Lex, LineCol, LineIndex, Span, Utf16Position};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::*;
+
+#[test]
+fn offsets_to_line_col() {
+    let index = LineIndex::new("int x\r\nstr é = \"😀\"\rend");
+    assert_eq!(index.line_count(), 3);
+    assert_eq!(index.line(1), Some("str é = \"😀\""));
+    assert_eq!(index.line_col(0), LineCol::new(0, 0));
+    assert_eq!(index.line_col(5), LineCol::new(0, 5));
+    // inside the CRLF
+    assert_eq!(index.line_col(6), LineCol::new(0, 5));
+    assert_eq!(index.line_col(7), LineCol::new(1, 0));
+    // after `é`, two bytes but one column
+    assert_eq!(index.line_col(13), LineCol::new(1, 5));
+    // inside `é`
+    assert_eq!(index.line_col(12), LineCol::new(1, 4));
+    assert_eq!(index.line_col(23), LineCol::new(2, 0));
+    assert_eq!(index.line_col(100), LineCol::new(2, 3));
+    assert_eq!(index.line_col(13).to_string(), "2:6");
+}
+
+#[test]
+fn line_col_to_offsets() {
+    let index = LineIndex::new("int x\r\nstr é\nend");
+    assert_eq!(index.offset(LineCol::new(1, 5)), Some(13));
+    assert_eq!(index.offset(LineCol::new(1, 50)), Some(13));
+    assert_eq!(index.offset(LineCol::new(2, 1)), Some(15));
+    assert_eq!(index.offset(LineCol::new(3, 0)), None);
+}
+
+#[test]
+fn utf16_positions() {
+    let index = LineIndex::new("a😀b\né");
+    assert_eq!(index.utf16(5), Utf16Position::new(0, 3));
+    assert_eq!(index.utf16(9), Utf16Position::new(1, 1));
+    assert_eq!(index.offset_utf16(Utf16Position::new(0, 3)), Some(5));
+    // between the surrogates of `😀`
+    assert_eq!(index.offset_utf16(Utf16Position::new(0, 2)), Some(1));
+    assert_eq!(index.offset_utf16(Utf16Position::new(1, 1)), Some(9));
+}
+
+#[test]
+fn tab_width() {
+    let index = LineIndex::new("\tx\n  \ty").with_tab_width(4);
+    assert_eq!(index.line_col(1), LineCol::new(0, 4));
+    assert_eq!(index.line_col(6), LineCol::new(1, 4));
+    assert_eq!(index.offset(LineCol::new(0, 4)), Some(1));
+    // inside the tab
+    assert_eq!(index.offset(LineCol::new(0, 2)), Some(0));
+    assert_eq!(LineIndex::new("\tx").line_col(1), LineCol::new(0, 1));
+}
+
+#[test]
+fn byte_order_mark() {
+    let index = LineIndex::new("\u{FEFF}ab\ncd");
+    assert_eq!(index.line(0), Some("ab"));
+    assert_eq!(index.line_col(0), LineCol::new(0, 0));
+    assert_eq!(index.line_col(4), LineCol::new(0, 1));
+    assert_eq!(index.offset(LineCol::new(0, 0)), Some(3));
+    assert_eq!(index.utf16(4), Utf16Position::new(0, 1));
+}
+
+#[test]
+fn lexed_spans() {
+    let source = "int x = 1\nx += \"é\"";
+    let index = LineIndex::new(source);
+    let lexed = Token::lex_spanned(source);
+    let string_span = lexed.tokens[6].span;
+    assert_eq!(string_span, Span::new(15, 19));
+    assert_eq!(
+        index.line_cols(string_span),
+        (LineCol::new(1, 5), LineCol::new(1, 8))
+    );
+    assert_eq!(
+        index.utf16_range(string_span),
+        (Utf16Position::new(1, 5), Utf16Position::new(1, 8))
+    );
+    assert_eq!(
+        index.span(LineCol::new(1, 5), LineCol::new(1, 8)),
+        Some(string_span)
+    );
+}
@@ -0,0 +1,71 @@
+use rcket::Lex;
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+#[lex(check)]
+enum Token<'a> {
+    #[token("..")]
+    Range,
+    #[token("-")]
+    Minus,
+    #[token("(")]
+    LeftParen,
+    #[token(")")]
+    RightParen,
+    #[regex(r"\d+\.\d*")]
+    #[not_followed_by(".")]
+    Float(f64),
+    // a minus sign after an operand is subtraction, not part of the literal
+    #[regex(r"-?\d+")]
+    #[not_preceded_by(regex(r"[\w)]+"))]
+    Int(i64),
+    #[regex(r"[a-z]+")]
+    #[followed_by(regex(r"\s*\("))]
+    Call(&'a str),
+    #[regex(r"[a-z]+")]
+    Identifier(&'a str),
+}
+
+#[test]
+fn range_is_not_a_float() {
+    assert_eq!(
+        Token::lex("1..2 3.5 4."),
+        vec![
+            Token::Int(1),
+            Token::Range,
+            Token::Int(2),
+            Token::Float(3.5),
+            Token::Float(4.0),
+        ]
+    );
+}
+
+#[test]
+fn minus_after_operand_is_subtraction() {
+    assert_eq!(
+        Token::lex("a-1 (-1)-3"),
+        vec![
+            Token::Identifier("a"),
+            Token::Minus,
+            Token::Int(1),
+            Token::LeftParen,
+            Token::Int(-1),
+            Token::RightParen,
+            Token::Minus,
+            Token::Int(3),
+        ]
+    );
+    assert_eq!(Token::lex("-4"), vec![Token::Int(-4)]);
+}
+
+#[test]
+fn followed_by_lookahead() {
+    assert_eq!(
+        Token::lex("f (x)"),
+        vec![
+            Token::Call("f"),
+            Token::LeftParen,
+            Token::Identifier("x"),
+            Token::RightParen,
+        ]
+    );
+}
@@ -0,0 +1,127 @@
+use rcket::parse::{MemoStats, memoize};
+use rcket::{Lex, Node};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::{Literal, Symbol, Token};
+
+#[path = "fixtures/counting_name.rs"]
+mod counting_name;
+use counting_name::{NAME_PARSES, Name};
+
+// both variants of `Term` start with a `Group`, so without memoization every level of nesting
+// parses the rest twice
+#[derive(Node, Debug, PartialEq, Clone)]
+#[node(memo)]
+enum Term {
+    Shout(Box<Group>, #[token(Symbol::Exclamation)] ()),
+    Plain(Box<Group>),
+}
+
+#[derive(Node, Debug, PartialEq, Clone)]
+#[node(memo)]
+enum Group {
+    Nested(
+        #[token(Symbol::LeftParen)] (),
+        Box<Term>,
+        #[token(Symbol::RightParen)] (),
+    ),
+    Name(Name),
+}
+
+fn nested(depth: usize) -> Vec<Token> {
+    Token::lex(&format!("{}x{}", "(".repeat(depth), ")".repeat(depth)))
+}
+
+fn name_parses(parse: impl FnOnce()) -> usize {
+    NAME_PARSES.with(|parses| parses.set(0));
+    parse();
+    NAME_PARSES.with(|parses| parses.get())
+}
+
+#[test]
+fn memoize_parses_each_position_once() {
+    let tokens = nested(10);
+    let plain = Term::parse(&tokens).unwrap();
+    // the `Term` around `x` doubles it once more
+    assert_eq!(name_parses(|| drop(Term::parse(&tokens))), 1 << 11);
+
+    let mut memoized = None;
+    let mut stats = MemoStats::default();
+    let parses = name_parses(|| (memoized, stats) = memoize(|| Term::parse(&tokens)));
+    assert_eq!(parses, 1);
+    assert_eq!(memoized, Some(plain));
+    // a `Term` and a `Group` at each of the 11 positions they start at, and a hit for each
+    // `Term` trying its second variant
+    assert_eq!(
+        stats,
+        MemoStats {
+            hits: 11,
+            misses: 22
+        }
+    );
+}
+
+#[test]
+fn memoize_keeps_results() {
+    let tokens = Token::lex("(x!)!");
+    let (term, _) = memoize(|| Term::parse(&tokens));
+    assert_eq!(
+        term,
+        Some(Term::Shout(
+            Box::new(Group::Nested(
+                (),
+                Box::new(Term::Shout(
+                    Box::new(Group::Name(Name("x".to_string()))),
+                    ()
+                )),
+                ()
+            )),
+            ()
+        ))
+    );
+    assert_eq!(memoize(|| Term::parse(&Token::lex("(x!"))).0, None);
+    // the table is only there during `memoize`
+    assert_eq!(memoize(|| ()).1, MemoStats::default());
+}
+
+#[test]
+fn memoize_tells_inputs_apart() {
+    // the tokens of the second input usually take the memory the first ones were dropped from
+    let ((first, second), stats) = memoize(|| {
+        let first = Term::parse(&Token::lex("(a)"));
+        (first, Term::parse(&Token::lex("(b)")))
+    });
+    assert_eq!(first, Term::parse(&Token::lex("(a)")));
+    assert_eq!(second, Term::parse(&Token::lex("(b)")));
+    // each input has a table of its own
+    assert_eq!(stats, MemoStats { hits: 4, misses: 8 });
+}
+
+#[derive(Node, Debug, PartialEq, Clone)]
+enum BinaryOperation {
+    #[infix(Symbol::Plus)]
+    #[prec(1)]
+    Addition(Box<Expression>, Box<Expression>),
+    #[infix(Symbol::Star)]
+    #[prec(2)]
+    Multiplication(Box<Expression>, Box<Expression>),
+}
+
+#[derive(Node, Debug, PartialEq, Clone)]
+#[node(memo)]
+enum Expression {
+    #[extract(Literal::Int)]
+    Int(i32),
+    BinaryOperation(BinaryOperation),
+}
+
+#[test]
+fn memoize_infix_operands() {
+    for input in ["1 + 2 * 3", "1 * 2 + 3", "1 + 2 + 3 * 4 * 5 + 6"] {
+        let tokens = Token::lex(input);
+        let (memoized, stats) = memoize(|| Expression::parse(&tokens));
+        assert_eq!(memoized, Expression::parse(&tokens), "{input}");
+        assert!(stats.misses > 0);
+    }
+}
@@ -0,0 +1,80 @@
+use rcket::{Lex, Node};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::{Keyword, Literal, Symbol, Token};
+
+#[derive(Node, Debug, PartialEq)]
+enum VarType {
+    #[token(Keyword::Int)]
+    Int,
+    #[token(Keyword::Str)]
+    Str,
+}
+
+#[derive(Node, Debug, PartialEq)]
+enum Expression {
+    #[extract(Literal::Int)]
+    Int(i32),
+    #[extract(Literal::String)]
+    String(String),
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct VariableDeclaration {
+    ty: VarType,
+    #[extract(Literal::Identifier)]
+    name: String,
+    #[token(Symbol::Equals)]
+    _eq: (),
+    value: Expression,
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct Grouped {
+    #[token(Symbol::LeftParen)]
+    _open: (),
+    inner: Box<Expression>,
+    #[token(Symbol::RightParen)]
+    _close: (),
+}
+
+#[test]
+fn parse_named_fields() {
+    let declaration = VariableDeclaration::parse(&Token::lex("int thing = 5")).unwrap();
+    assert_eq!(
+        declaration,
+        VariableDeclaration {
+            ty: VarType::Int,
+            name: "thing".to_string(),
+            _eq: (),
+            value: Expression::Int(5),
+        }
+    );
+    assert_eq!(
+        declaration.to_string(),
+        "VariableDeclaration (Int thing Expression (Int (5)))"
+    );
+}
+
+#[test]
+fn named_fields_keep_declaration_order() {
+    assert_eq!(
+        VariableDeclaration::parse(&Token::lex("thing int = 5")),
+        None
+    );
+    assert_eq!(
+        VariableDeclaration::parse(&Token::lex("str thing \"text\"")),
+        None
+    );
+}
+
+#[test]
+fn parse_boxed_named_field() {
+    let grouped = Grouped::parse(&Token::lex("(\"text\")")).unwrap();
+    assert_eq!(
+        grouped.inner,
+        Box::new(Expression::String("text".to_string()))
+    );
+    assert_eq!(grouped.to_string(), "Grouped (Expression (String (text)))");
+}
@@ -0,0 +1,108 @@
+use rcket::{Lex, Node};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::{Keyword, Literal, Symbol, Token};
+
+#[derive(Node, Debug, PartialEq)]
+enum VarType {
+    #[token(Keyword::Int)]
+    Int,
+    #[token(Keyword::Str)]
+    Str,
+}
+
+#[derive(Node, Debug, PartialEq)]
+enum Value {
+    #[extract(Literal::Int)]
+    Int(i32),
+    #[extract(Literal::String)]
+    String(String),
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct DefaultValue(#[token(Symbol::Equals)] (), Value);
+
+#[derive(Node, Debug, PartialEq)]
+struct Parameter {
+    #[token(Symbol::Ampersand)]
+    reference: bool,
+    ty: VarType,
+    #[extract(Literal::Identifier)]
+    name: String,
+    default: Option<DefaultValue>,
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct Return(
+    #[token(Keyword::Return)] (),
+    Option<Box<Value>>,
+    #[token(Symbol::Exclamation)] Option<()>,
+);
+
+#[derive(Node, Debug, PartialEq)]
+enum Statement {
+    Label(
+        #[extract(Literal::Identifier)] Option<String>,
+        #[token(Symbol::Colon)] (),
+    ),
+}
+
+#[test]
+fn optional_node_field() {
+    let parameter = Parameter::parse(&Token::lex("int size = 3")).unwrap();
+    assert_eq!(
+        parameter,
+        Parameter {
+            reference: false,
+            ty: VarType::Int,
+            name: "size".to_string(),
+            default: Some(DefaultValue((), Value::Int(3))),
+        }
+    );
+    assert_eq!(
+        parameter.to_string(),
+        "Parameter (Int size DefaultValue (Value (Int (3))))"
+    );
+
+    let parameter = Parameter::parse(&Token::lex("&str name")).unwrap();
+    assert!(parameter.reference);
+    assert_eq!(parameter.default, None);
+    assert_eq!(parameter.to_string(), "Parameter (Str name)");
+}
+
+#[test]
+fn missing_optional_consumes_nothing() {
+    assert_eq!(
+        Return::parse(&Token::lex("return")).unwrap(),
+        Return((), None, None)
+    );
+    assert_eq!(
+        Return::parse(&Token::lex("return 5 !")).unwrap(),
+        Return((), Some(Box::new(Value::Int(5))), Some(()))
+    );
+    assert_eq!(
+        Return::parse(&Token::lex("return !")).unwrap(),
+        Return((), None, Some(()))
+    );
+    let tokens = Token::lex("return int");
+    let (node, rest) = Return::parse_one(&tokens).unwrap();
+    assert_eq!(node, Return((), None, None));
+    assert_eq!(rest, &tokens[1..]);
+}
+
+#[test]
+fn optional_extract() {
+    assert_eq!(
+        Statement::parse(&Token::lex("outer:")).unwrap(),
+        Statement::Label(Some("outer".to_string()), ())
+    );
+    assert_eq!(
+        Statement::parse(&Token::lex(":")).unwrap(),
+        Statement::Label(None, ())
+    );
+    assert_eq!(
+        Statement::Label(None, ()).to_string(),
+        "Statement (Label ())"
+    );
+}
@@ -66,6 +66,9 @@ enum Statement {
     VariableAssignment(VariableAssignment),
 }
 
+#[derive(Node, Debug, PartialEq)]
+struct Program(Vec<Statement>);
+
 #[test]
 fn parse_int() {
     let value = Expression::parse(&Token::lex("1225")).unwrap();
@@ -86,7 +89,33 @@ fn parse_add_operation() {
 #[test]
 fn parse_larger_operations() {
     let node = BinaryOperation::parse(&Token::lex("5+2*7")).unwrap();
-    assert_eq!(node.to_string(), "TODO");
+    assert_eq!(
+        node.to_string(),
+        "BinaryOperation (AdditionOperation (Expression (Int (5)) Expression (BinaryOperation (MultiplicationOperation (Expression (Int (2)) Expression (Int (7)))))))"
+    );
+    let node = BinaryOperation::parse(&Token::lex("5*2+7")).unwrap();
+    assert_eq!(
+        node.to_string(),
+        "BinaryOperation (AdditionOperation (Expression (BinaryOperation (MultiplicationOperation (Expression (Int (5)) Expression (Int (2))))) Expression (Int (7))))"
+    );
+}
+
+#[test]
+fn operations_group_left() {
+    assert_eq!(
+        Expression::parse(&Token::lex("1+2+3")).unwrap(),
+        Expression::BinaryOperation(BinaryOperation::AdditionOperation(
+            Box::new(Expression::BinaryOperation(
+                BinaryOperation::AdditionOperation(
+                    Box::new(Expression::Int(1)),
+                    Box::new(Expression::Int(2)),
+                )
+            )),
+            Box::new(Expression::Int(3)),
+        ))
+    );
+    assert_eq!(BinaryOperation::parse(&Token::lex("1")), None);
+    assert_eq!(Expression::parse(&Token::lex("1+")), None);
 }
 
 #[test]
@@ -135,6 +164,22 @@ fn parse_assignment_operation() {
         VariableAssignment::parse(&Token::lex("OtherThing += 5 * 3"))
             .unwrap()
             .to_string(),
-        "TODO"
+        "VariableAssignment (OtherThing PlusEqual Expression (BinaryOperation (MultiplicationOperation (Expression (Int (5)) Expression (Int (3))))))"
     )
 }
+
+#[test]
+fn parse_program() {
+    let program = Program::parse(&Token::lex("int a = 1 + 2 a += 3 str b = \"text\"")).unwrap();
+    assert_eq!(program.0.len(), 3);
+    assert_eq!(
+        program.0[1],
+        Statement::VariableAssignment(VariableAssignment(
+            "a".to_string(),
+            AssignmentOp::PlusEqual,
+            Expression::Int(3)
+        ))
+    );
+    assert_eq!(Program::parse(&[]).unwrap(), Program(vec![]));
+    assert_eq!(Program::parse(&Token::lex("int a = 1 +")), None);
+}
@@ -0,0 +1,123 @@
+use rcket::lex::builtin;
+use rcket::pass::{ExpandMacros, InsertSemicolons, JoinAdjacent, Remove};
+use rcket::{Lex, Span, Spanned, TokenPass};
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Token<'a> {
+    #[token(";")]
+    Semicolon,
+    #[token("=")]
+    Equals,
+    #[token(">")]
+    Greater,
+    #[token(")")]
+    RightParen,
+    // only produced by joining
+    #[token(">>")]
+    #[lex(priority = 0)]
+    ShiftRight,
+    #[matcher(builtin::line_comment)]
+    #[lex(priority = 2)]
+    Comment,
+    #[matcher(builtin::int)]
+    Int(i64),
+    #[matcher(builtin::identifier)]
+    Identifier(&'a str),
+}
+
+fn ends_statement(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Int(_) | Token::Identifier(_) | Token::RightParen
+    )
+}
+
+#[test]
+fn insert_semicolons() {
+    let source = "x = 1\ny = x\n\nf )";
+    let lexed = Token::lex_spanned(source).apply(InsertSemicolons::new(
+        source,
+        Token::Semicolon,
+        ends_statement,
+    ));
+    assert_eq!(
+        lexed.tokens,
+        vec![
+            Spanned::new(Token::Identifier("x"), Span::new(0, 1)),
+            Spanned::new(Token::Equals, Span::new(2, 3)),
+            Spanned::new(Token::Int(1), Span::new(4, 5)),
+            Spanned::new(Token::Semicolon, Span::new(5, 5)),
+            Spanned::new(Token::Identifier("y"), Span::new(6, 7)),
+            Spanned::new(Token::Equals, Span::new(8, 9)),
+            Spanned::new(Token::Identifier("x"), Span::new(10, 11)),
+            Spanned::new(Token::Semicolon, Span::new(11, 11)),
+            Spanned::new(Token::Identifier("f"), Span::new(13, 14)),
+            Spanned::new(Token::RightParen, Span::new(15, 16)),
+            Spanned::new(Token::Semicolon, Span::new(16, 16)),
+        ]
+    );
+}
+
+#[test]
+fn join_adjacent() {
+    let mut join = JoinAdjacent::new(|first: &Token, second: &Token| match (first, second) {
+        (Token::Greater, Token::Greater) => Some(Token::ShiftRight),
+        _ => None,
+    });
+    let tokens = join.run(Token::lex_spanned("a >> 1 > >").tokens);
+    assert_eq!(
+        tokens,
+        vec![
+            Spanned::new(Token::Identifier("a"), Span::new(0, 1)),
+            Spanned::new(Token::ShiftRight, Span::new(2, 4)),
+            Spanned::new(Token::Int(1), Span::new(5, 6)),
+            Spanned::new(Token::Greater, Span::new(7, 8)),
+            Spanned::new(Token::Greater, Span::new(9, 10)),
+        ]
+    );
+}
+
+#[test]
+fn expand_macros() {
+    let mut expand = ExpandMacros::new()
+        .define(Token::Identifier("MAX"), vec![Token::Int(10)])
+        .define(
+            Token::Identifier("LIMIT"),
+            vec![Token::Identifier("MAX"), Token::Identifier("LIMIT")],
+        );
+    let tokens = expand.run(Token::lex_spanned("x = LIMIT").tokens);
+    assert_eq!(
+        tokens,
+        vec![
+            Spanned::new(Token::Identifier("x"), Span::new(0, 1)),
+            Spanned::new(Token::Equals, Span::new(2, 3)),
+            Spanned::new(Token::Int(10), Span::new(4, 9)),
+            Spanned::new(Token::Identifier("LIMIT"), Span::new(4, 9)),
+        ]
+    );
+}
+
+#[test]
+fn passes_compose() {
+    let source = "a = b // note\nc";
+    let pass = Remove::new(|token: &Token| *token == Token::Comment)
+        .then(InsertSemicolons::new(
+            source,
+            Token::Semicolon,
+            ends_statement,
+        ))
+        .then(|mut tokens: Vec<Spanned<Token<'static>>>| {
+            tokens.retain(|token| token.value != Token::Equals);
+            tokens
+        });
+    assert_eq!(
+        Token::lex_spanned(source).apply(pass).into_values(),
+        vec![
+            Token::Identifier("a"),
+            Token::Identifier("b"),
+            Token::Semicolon,
+            Token::Identifier("c"),
+            Token::Semicolon,
+        ]
+    );
+}
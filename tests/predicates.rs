@@ -0,0 +1,116 @@
+use rcket::{Lex, Node};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::{Keyword, Literal, Symbol, Token};
+
+#[derive(Node, Debug, PartialEq)]
+struct Call(
+    #[extract(Literal::Identifier)] String,
+    #[token(Symbol::LeftParen)] (),
+    #[sep(Symbol::Comma)] Vec<Atom>,
+    #[token(Symbol::RightParen)] (),
+);
+
+/// An identifier not followed by `(`, which would make it a call.
+#[derive(Node, Debug, PartialEq)]
+struct Variable(
+    #[extract(Literal::Identifier)] String,
+    #[not(token = Symbol::LeftParen)] (),
+);
+
+#[derive(Node, Debug, PartialEq)]
+enum Atom {
+    #[extract(Literal::Int)]
+    Int(i32),
+    Variable(Variable),
+    Call(Call),
+}
+
+/// The name of a call, without the call.
+#[derive(Node, Debug, PartialEq)]
+struct Callee(
+    #[peek(node = Call)] (),
+    #[extract(Literal::Identifier)] String,
+);
+
+/// An identifier that's followed by `:`, which is left for the parent.
+#[derive(Node, Debug, PartialEq)]
+struct Label(
+    #[extract(Literal::Identifier)] String,
+    #[peek(token = Symbol::Colon)] (),
+);
+
+#[derive(Node, Debug, PartialEq)]
+struct Block(
+    #[token(Symbol::LeftBrace)] (),
+    #[token(Symbol::RightBrace)] (),
+);
+
+#[derive(Node, Debug, PartialEq)]
+struct Else(#[token(Keyword::Else)] (), Block);
+
+#[derive(Node, Debug, PartialEq)]
+struct If(#[token(Keyword::If)] (), Atom, Block, Option<Else>);
+
+/// Anything but an `else`, which only belongs to the `if` before it.
+#[derive(Node, Debug, PartialEq)]
+struct Statement(#[not(token = Keyword::Else)] (), Option<If>, Option<Atom>);
+
+fn variable(name: &str) -> Atom {
+    Atom::Variable(Variable(name.to_string(), ()))
+}
+
+#[test]
+fn not_token() {
+    assert_eq!(Atom::parse(&Token::lex("x")), Some(variable("x")));
+    assert_eq!(
+        Atom::parse(&Token::lex("f(x, 1)")),
+        Some(Atom::Call(Call(
+            "f".to_string(),
+            (),
+            vec![variable("x"), Atom::Int(1)],
+            ()
+        )))
+    );
+    assert_eq!(Variable::parse(&Token::lex("f(")), None);
+
+    assert_eq!(
+        Statement::parse(&Token::lex("if x {} else {}")),
+        Some(Statement(
+            (),
+            Some(If(
+                (),
+                variable("x"),
+                Block((), ()),
+                Some(Else((), Block((), ())))
+            )),
+            None
+        ))
+    );
+    assert_eq!(Statement::parse_one(&Token::lex("else {}")), None);
+}
+
+#[test]
+fn peek_node() {
+    let tokens = Token::lex("f(1) g");
+    let (callee, rest) = Callee::parse_one(&tokens).unwrap();
+    assert_eq!(callee, Callee((), "f".to_string()));
+    assert_eq!(rest.len(), 4);
+    assert_eq!(Callee::parse_one(&tokens[4..]), None);
+    // failures inside the lookahead aren't reported
+    let error = Callee::try_parse(&Token::lex("f(1")).unwrap_err();
+    assert_eq!(error.position, 0);
+}
+
+#[test]
+fn peek_token() {
+    let tokens = Token::lex("x: 1");
+    let (label, rest) = Label::parse_one(&tokens).unwrap();
+    assert_eq!(label, Label("x".to_string(), ()));
+    assert_eq!(rest, &tokens[1..]);
+
+    let error = Label::try_parse(&Token::lex("x")).unwrap_err();
+    assert_eq!(error.position, 1);
+    assert_eq!(error.expected, vec!["':'".to_string()]);
+}
@@ -0,0 +1,80 @@
+use rcket::{Lex, Node, Project};
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Punct {
+    #[token("=")]
+    Assign,
+    #[token(";")]
+    Semi,
+}
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Number {
+    #[regex(r"\d+")]
+    Int(i64),
+}
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Kw {
+    #[token("let")]
+    Let,
+}
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Word<'a> {
+    #[regex(r"[a-z]+")]
+    Name(&'a str),
+}
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Lexeme<'a> {
+    Op(Punct),
+    Num(Number),
+    Reserved(Kw),
+    Text(Word<'a>),
+    #[token(",")]
+    Comma,
+}
+
+#[derive(Node, Debug, PartialEq)]
+#[node(token = Lexeme<'a>)]
+struct Let<'a>(
+    #[token(Kw::Let)] (),
+    #[extract(Word::Name)] &'a str,
+    #[token(Punct::Assign)] (),
+    Value<'a>,
+    #[token(Punct::Semi)] (),
+);
+
+#[derive(Node, Debug, PartialEq)]
+#[node(token = Lexeme<'a>)]
+enum Value<'a> {
+    #[extract(Number::Int)]
+    Int(i64),
+    #[extract(Word::Name)]
+    Name(&'a str),
+    #[token(Lexeme::Comma)]
+    Comma,
+}
+
+#[test]
+fn project_wrapped_tokens() {
+    let token = Lexeme::Op(Punct::Semi);
+    assert_eq!(Project::<Punct>::project(&token), Some(&Punct::Semi));
+    assert_eq!(Project::<Number>::project(&token), None);
+    assert_eq!(Project::<Lexeme>::project(&token), Some(&token));
+}
+
+#[test]
+fn parse_with_custom_wrapper_names() {
+    let tokens = Lexeme::lex("let x = 5;");
+    assert_eq!(
+        Let::parse(&tokens),
+        Some(Let((), "x", (), Value::Int(5), ()))
+    );
+    let tokens = Lexeme::lex("let x = ,;");
+    assert_eq!(
+        Let::parse(&tokens),
+        Some(Let((), "x", (), Value::Comma, ()))
+    );
+}
@@ -0,0 +1,177 @@
+use rcket::{Lex, Node, ParseErrorKind};
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Token {
+    #[token("{")]
+    LeftBrace,
+    #[token("}")]
+    RightBrace,
+    #[token(";")]
+    Semicolon,
+    #[token("=")]
+    Equals,
+    #[regex(r"\d+")]
+    Int(i32),
+    #[regex("[a-z]+")]
+    Name(String),
+}
+
+#[derive(Node, Debug, PartialEq)]
+enum Value {
+    #[extract(Token::Int)]
+    Int(i32),
+    #[extract(Token::Name)]
+    Name(String),
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct Assignment(
+    #[extract(Token::Name)] String,
+    #[token(Token::Equals)] (),
+    Value,
+    #[token(Token::Semicolon)] (),
+);
+
+#[derive(Node, Debug, PartialEq)]
+struct Block(
+    #[token(Token::LeftBrace)] (),
+    Vec<Statement>,
+    #[token(Token::RightBrace)] (),
+);
+
+#[derive(Node, Debug, PartialEq)]
+#[recover(after(Token::Semicolon), before(Token::RightBrace))]
+enum Statement {
+    Assignment(Assignment),
+    Block(Block),
+    #[error]
+    Error(Vec<Token>),
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct Program(Vec<Statement>);
+
+fn assignment(name: &str, value: i32) -> Statement {
+    Statement::Assignment(Assignment(name.to_string(), (), Value::Int(value), ()))
+}
+
+#[test]
+fn skips_to_sync_tokens() {
+    let (program, errors) = Program::parse_with_errors(&Token::lex("a = 1; b = ; c = 3;"));
+    assert_eq!(
+        program,
+        Some(Program(vec![
+            assignment("a", 1),
+            Statement::Error(vec![
+                Token::Name("b".to_string()),
+                Token::Equals,
+                Token::Semicolon
+            ]),
+            assignment("c", 3),
+        ]))
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].position, 6);
+    assert_eq!(errors[0].kind, ParseErrorKind::UnexpectedToken);
+    assert_eq!(errors[0].expected, vec!["int", "name"]);
+}
+
+#[test]
+fn collects_every_error() {
+    let tokens = Token::lex("{ a = 1; b 2; } c = = ; { d = 4; e }");
+    let (program, errors) = Program::parse_with_errors(&tokens);
+    assert_eq!(
+        program,
+        Some(Program(vec![
+            Statement::Block(Block(
+                (),
+                vec![
+                    assignment("a", 1),
+                    Statement::Error(vec![
+                        Token::Name("b".to_string()),
+                        Token::Int(2),
+                        Token::Semicolon
+                    ]),
+                ],
+                ()
+            )),
+            Statement::Error(vec![
+                Token::Name("c".to_string()),
+                Token::Equals,
+                Token::Equals,
+                Token::Semicolon
+            ]),
+            Statement::Block(Block(
+                (),
+                vec![
+                    assignment("d", 4),
+                    // the closing brace is left for the block
+                    Statement::Error(vec![Token::Name("e".to_string())]),
+                ],
+                ()
+            )),
+        ]))
+    );
+    let positions: Vec<usize> = errors.iter().map(|error| error.position).collect();
+    assert_eq!(positions, vec![6, 11, 19]);
+    assert_eq!(errors[0].expected, vec!["'='"]);
+    assert_eq!(
+        errors[2].to_string(),
+        "unexpected token at 19, expected '='"
+    );
+}
+
+#[test]
+fn recovers_only_when_asked() {
+    let tokens = Token::lex("a = 1; b = ;");
+    assert_eq!(Program::parse(&tokens), None);
+    assert_eq!(Program::try_parse(&tokens).unwrap_err().position, 6);
+
+    let (program, errors) = Program::parse_with_errors(&Token::lex("a = 1; b = 2;"));
+    assert_eq!(
+        program,
+        Some(Program(vec![assignment("a", 1), assignment("b", 2)]))
+    );
+    assert!(errors.is_empty());
+
+    // nothing to skip before the stray brace, so the program stops there
+    let (program, errors) = Program::parse_with_errors(&Token::lex("a = 1; }"));
+    assert_eq!(program, Some(Program(vec![assignment("a", 1)])));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].position, 4);
+}
+
+/// Panics when it's parsed, like a node with a bug.
+#[derive(Debug, PartialEq)]
+struct Panics;
+
+impl Node for Panics {
+    type Token = Token;
+    type Output = Self;
+
+    fn parse_one(_tokens: &[Token]) -> Option<(Self, &[Token])> {
+        panic!("a node panicked")
+    }
+}
+
+impl std::fmt::Display for Panics {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "panics")
+    }
+}
+
+#[derive(Node, Debug, PartialEq)]
+#[recover(after(Token::Semicolon))]
+enum Buggy {
+    Panics(Panics),
+    #[error]
+    Error(Vec<Token>),
+}
+
+#[test]
+fn panics_end_recovery() {
+    let tokens = Token::lex("a;");
+    assert!(std::panic::catch_unwind(|| Buggy::parse_with_errors(&tokens)).is_err());
+    // parses after the panic don't recover
+    assert_eq!(Statement::parse(&Token::lex("a = ;")), None);
+}
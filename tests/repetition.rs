@@ -0,0 +1,94 @@
+use rcket::{Lex, Node};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::{Literal, Symbol, Token};
+
+#[derive(Node, Debug, PartialEq)]
+enum Value {
+    #[extract(Literal::Int)]
+    Int(i32),
+    #[extract(Literal::Identifier)]
+    Name(String),
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct Call {
+    #[extract(Literal::Identifier)]
+    name: String,
+    #[token(Symbol::LeftParen)]
+    _open: (),
+    #[sep(Symbol::Comma)]
+    arguments: Vec<Value>,
+    #[token(Symbol::RightParen)]
+    _close: (),
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct List(
+    #[token(Symbol::LeftBracket)] (),
+    #[sep(Symbol::Comma, trailing)]
+    #[many1]
+    Vec<Value>,
+    #[token(Symbol::RightBracket)] (),
+);
+
+#[derive(Node, Debug, PartialEq)]
+struct Names(
+    #[extract(Literal::Identifier)]
+    #[many(min = 2, max = 3)]
+    Vec<String>,
+);
+
+fn values(tokens: &[Token]) -> Vec<Value> {
+    Call::parse(tokens).unwrap().arguments
+}
+
+#[test]
+fn separated_elements() {
+    assert_eq!(values(&Token::lex("f()")), vec![]);
+    assert_eq!(values(&Token::lex("f(1)")), vec![Value::Int(1)]);
+    assert_eq!(
+        values(&Token::lex("f(1, x, 3)")),
+        vec![Value::Int(1), Value::Name("x".to_string()), Value::Int(3)]
+    );
+    assert_eq!(
+        Call::parse(&Token::lex("f(1, x)")).unwrap().to_string(),
+        "Call (f Value (Int (1)) Value (Name (x)))"
+    );
+    assert_eq!(Call::parse(&Token::lex("f(1 2)")), None);
+    assert_eq!(Call::parse(&Token::lex("f(1,)")), None);
+    assert_eq!(Call::parse(&Token::lex("f(,1)")), None);
+}
+
+#[test]
+fn trailing_separator_and_minimum() {
+    let list = List::parse(&Token::lex("[1, 2,]")).unwrap();
+    assert_eq!(list.1, vec![Value::Int(1), Value::Int(2)]);
+    assert!(List::parse(&Token::lex("[1, 2]")).is_some());
+    assert_eq!(List::parse(&Token::lex("[]")), None);
+    assert_eq!(List::parse(&Token::lex("[,]")), None);
+}
+
+#[test]
+fn count_limits() {
+    assert_eq!(Names::parse(&Token::lex("a")), None);
+    assert_eq!(
+        Names::parse(&Token::lex("a b c")).unwrap().0,
+        vec!["a", "b", "c"]
+    );
+    let tokens = Token::lex("a b c d");
+    let (names, rest) = Names::parse_one(&tokens).unwrap();
+    assert_eq!(names.0.len(), 3);
+    assert_eq!(rest.len(), 1);
+}
+
+#[test]
+fn missing_separator_is_expected() {
+    let error = Call::try_parse(&Token::lex("f(a, 1 2)")).unwrap_err();
+    assert_eq!(error.position, 5);
+    assert_eq!(
+        error.to_string(),
+        "unexpected token at 5, expected ',' or ')'"
+    );
+}
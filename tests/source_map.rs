@@ -0,0 +1,71 @@
+use rcket::{FileId, Lex, LineCol, Node, SourceMap, Span, Spanned};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::*;
+
+#[derive(Node, Debug, PartialEq)]
+struct Assignment(
+    #[extract(Literal::Identifier)] String,
+    #[token(Symbol::Equals)] (),
+    #[extract(Literal::Int)] i32,
+);
+
+#[test]
+fn files_get_distinct_spans() {
+    let mut sources = SourceMap::new();
+    let main = sources.add("main.frg", "x = 1");
+    let lib = sources.add("lib.frg", "y = 2");
+    assert_eq!(main, FileId(0));
+    assert_eq!(lib, FileId(1));
+    assert_eq!(sources.find("lib.frg"), Some(lib));
+    assert_eq!(sources.files().collect::<Vec<_>>(), vec![main, lib]);
+
+    let main_tokens = sources.lex::<Token>(main).tokens;
+    let lib_tokens = sources.lex::<Token>(lib).tokens;
+    assert_eq!(main_tokens[0].span, Span::new(0, 1).with_file(main));
+    assert_eq!(lib_tokens[0].span, Span::new(0, 1).with_file(lib));
+    assert_ne!(main_tokens[0].span, lib_tokens[0].span);
+}
+
+#[test]
+fn lexing_without_a_file_uses_none() {
+    assert_eq!(Token::lex_spanned("x").tokens[0].span.file, FileId::NONE);
+    assert_eq!(FileId::default(), FileId::NONE);
+
+    let mut sources = SourceMap::new();
+    let main = sources.add("main.frg", "x");
+    assert_ne!(FileId::default(), main);
+}
+
+#[test]
+fn errors_resolve_to_file_and_line() {
+    let mut sources = SourceMap::new();
+    sources.add("main.frg", "x = 1");
+    let lib = sources.add("lib.frg", "int y = 2\ny = @");
+    let lexed = sources.lex::<Token>(lib);
+
+    let location = sources.locate(lexed.errors[0].span);
+    assert_eq!(location.name, "lib.frg");
+    assert_eq!(location.start, LineCol::new(1, 4));
+    assert_eq!(location.to_string(), "lib.frg:2:5");
+    assert_eq!(
+        sources.describe(&lexed.errors[0]),
+        "lib.frg:2:5: unrecognized input \"@\""
+    );
+}
+
+#[test]
+fn parsed_nodes_keep_their_file() {
+    let mut sources = SourceMap::new();
+    sources.add("main.frg", "");
+    let lib = sources.add("lib.frg", "  y = 2");
+    let tokens = sources.lex::<Token>(lib).tokens;
+    assert_eq!(
+        Assignment::parse_file(tokens),
+        Some(Spanned::new(
+            Assignment("y".to_string(), (), 2),
+            Span::new(2, 7).with_file(lib)
+        ))
+    );
+}
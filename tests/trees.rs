@@ -0,0 +1,142 @@
+use rcket::tree::{DelimiterError, Group, TokenTree, TokenTrees};
+use rcket::{Lex, Span, Spanned};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::*;
+
+fn trees(input: &str) -> TokenTrees<Token> {
+    TokenTrees::new(Token::lex_spanned(input).tokens)
+}
+
+fn symbol(symbol: Symbol, start: usize) -> Spanned<Token> {
+    Spanned::new(Token::Symbol(symbol), Span::new(start, start + 1))
+}
+
+fn identifier(name: &str, start: usize) -> TokenTree<Token> {
+    TokenTree::Token(Spanned::new(
+        Token::Literal(Literal::Identifier(name.to_string())),
+        Span::new(start, start + name.len()),
+    ))
+}
+
+#[test]
+fn nested_groups() {
+    let token_trees = trees("f(a[b]) {}");
+    assert_eq!(token_trees.errors, vec![]);
+    assert_eq!(
+        token_trees.trees,
+        vec![
+            identifier("f", 0),
+            TokenTree::Group(Group {
+                open: symbol(Symbol::LeftParen, 1),
+                trees: vec![
+                    identifier("a", 2),
+                    TokenTree::Group(Group {
+                        open: symbol(Symbol::LeftBracket, 3),
+                        trees: vec![identifier("b", 4)],
+                        close: Some(symbol(Symbol::RightBracket, 5)),
+                    }),
+                ],
+                close: Some(symbol(Symbol::RightParen, 6)),
+            }),
+            TokenTree::Group(Group {
+                open: symbol(Symbol::LeftBrace, 8),
+                trees: vec![],
+                close: Some(symbol(Symbol::RightBrace, 9)),
+            }),
+        ]
+    );
+    assert_eq!(token_trees.trees[1].span(), Span::new(1, 7));
+}
+
+#[test]
+fn mismatched_delimiter() {
+    let token_trees = trees("{ (a }");
+    assert_eq!(
+        token_trees.errors,
+        vec![DelimiterError::Mismatched {
+            open: Span::new(2, 3),
+            close: Span::new(5, 6),
+        }]
+    );
+    assert_eq!(
+        token_trees.errors[0].to_string(),
+        "delimiter at 5..6 doesn't match the one opened at 2..3"
+    );
+    assert_eq!(
+        token_trees.trees,
+        vec![TokenTree::Group(Group {
+            open: symbol(Symbol::LeftBrace, 0),
+            trees: vec![TokenTree::Group(Group {
+                open: symbol(Symbol::LeftParen, 2),
+                trees: vec![identifier("a", 3)],
+                close: None,
+            })],
+            close: Some(symbol(Symbol::RightBrace, 5)),
+        })]
+    );
+}
+
+#[test]
+fn wrong_closing_delimiter() {
+    let token_trees = trees("{ (a] }");
+    assert_eq!(
+        token_trees.errors,
+        vec![DelimiterError::Mismatched {
+            open: Span::new(2, 3),
+            close: Span::new(4, 5),
+        }]
+    );
+    assert_eq!(
+        token_trees.trees,
+        vec![TokenTree::Group(Group {
+            open: symbol(Symbol::LeftBrace, 0),
+            trees: vec![TokenTree::Group(Group {
+                open: symbol(Symbol::LeftParen, 2),
+                trees: vec![identifier("a", 3)],
+                close: Some(symbol(Symbol::RightBracket, 4)),
+            })],
+            close: Some(symbol(Symbol::RightBrace, 6)),
+        })]
+    );
+
+    let token_trees = trees("(]");
+    assert_eq!(
+        token_trees.errors,
+        vec![DelimiterError::Mismatched {
+            open: Span::new(0, 1),
+            close: Span::new(1, 2),
+        }]
+    );
+}
+
+#[test]
+fn unclosed_and_unopened_delimiters() {
+    let token_trees = trees("a) { [b");
+    assert_eq!(
+        token_trees.errors,
+        vec![
+            DelimiterError::Unopened {
+                close: Span::new(1, 2),
+            },
+            DelimiterError::Unclosed {
+                open: Span::new(3, 4),
+                end: Span::new(7, 7),
+            },
+            DelimiterError::Unclosed {
+                open: Span::new(5, 6),
+                end: Span::new(7, 7),
+            },
+        ]
+    );
+    assert_eq!(token_trees.trees.len(), 3);
+    assert_eq!(token_trees.trees[2].span(), Span::new(3, 7));
+}
+
+#[test]
+fn flatten_restores_tokens() {
+    let tokens = Token::lex_spanned("f(a[b]) {").tokens;
+    let token_trees = TokenTrees::new(tokens.clone());
+    assert_eq!(TokenTree::flatten(token_trees.trees), tokens);
+}
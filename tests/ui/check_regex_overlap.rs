@@ -0,0 +1,21 @@
+use rcket::Lex;
+
+#[derive(Lex, Debug)]
+#[lex(check)]
+enum Number {
+    #[regex("[0-9][0-9]*")]
+    Digits(String),
+    #[regex("[0-9]+")]
+    Integer(String),
+}
+
+#[derive(Lex, Debug)]
+#[lex(check)]
+enum Word {
+    #[regex("[a-z_]+")]
+    Name(String),
+    #[regex("[a-z]+")]
+    Lowercase(String),
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use rcket::Lex;
+
+#[derive(Lex, Debug)]
+#[lex(check)]
+enum Token {
+    #[token("=>")]
+    Arrow,
+    #[regex("=>")]
+    FatArrow,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use rcket::Lex;
+
+#[derive(Lex, Debug)]
+#[lex(check)]
+enum Token {
+    #[regex("[a-z]+")]
+    Identifier,
+    #[token("if")]
+    If,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use rcket::Lex;
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Symbol {
+    #[token("+")]
+    Plus,
+}
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Token {
+    Symbol(Symbol),
+    Operator(Symbol),
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use rcket::Lex;
+
+#[derive(Lex, Debug)]
+enum Token {
+    #[token("+")]
+    Plus,
+    #[regex("[0-9]*")]
+    Digits,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use rcket::Lex;
+
+#[derive(Lex, Debug)]
+enum Token {
+    #[regex("[a-z")]
+    Name,
+}
+
+fn main() {}
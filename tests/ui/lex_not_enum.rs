@@ -0,0 +1,6 @@
+use rcket::Lex;
+
+#[derive(Lex)]
+struct Token(String);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use rcket::Lex;
+
+struct Nothing;
+
+impl<'src> Lex<'src> for Nothing {}
+
+fn main() {
+    Nothing::lex("x");
+}
@@ -0,0 +1,10 @@
+use rcket::Lex;
+
+#[derive(Lex, Debug)]
+#[lex(bytes)]
+enum Token {
+    #[matcher(rcket::lex::builtin::int)]
+    Int(u64),
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use rcket::Lex;
+use rcket::lex::builtin::identifier;
+
+#[derive(Lex, Debug)]
+#[lex(bytes)]
+enum Token {
+    #[matcher(identifier)]
+    Name,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use rcket::Node;
+
+#[derive(Node)]
+union Value {
+    int: i32,
+    float: f32,
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use rcket::{Lex, Node};
+
+#[derive(Lex, Debug, PartialEq, Clone)]
+enum Token {
+    #[token(":")]
+    Colon,
+    #[regex("[a-z]+")]
+    Name(String),
+}
+
+#[derive(Node, Debug, PartialEq)]
+struct Label(#[extract(Token::Name)] String, #[peek(Token::Colon)] ());
+
+fn main() {}
@@ -0,0 +1,100 @@
+use rcket::{Lex, Node};
+
+#[path = "frg_lexer_types.rs"]
+mod frg_lexer_types;
+use frg_lexer_types::{Keyword, Literal, Symbol, Token};
+
+#[derive(Node, Debug, PartialEq)]
+enum Value {
+    #[extract(Literal::Int)]
+    Int(i32),
+    #[extract(Literal::Identifier)]
+    Name(String),
+}
+
+#[derive(Node, Debug, PartialEq)]
+enum Expression {
+    Call(
+        #[extract(Literal::Identifier)] String,
+        #[token(Symbol::LeftParen)] (),
+        Value,
+        #[token(Symbol::RightParen)] (),
+    ),
+    Index {
+        #[extract(Literal::Identifier)]
+        target: String,
+        #[token(Symbol::LeftBracket)]
+        _open: (),
+        index: Box<Value>,
+        #[token(Symbol::RightBracket)]
+        _close: (),
+    },
+    Value(Value),
+}
+
+#[derive(Node, Debug, PartialEq)]
+enum Statement {
+    Return(#[token(Keyword::Return)] (), Expression),
+    Expression(Expression),
+}
+
+#[derive(Node, Debug, PartialEq)]
+enum Ending {
+    #[token(Symbol::Period)]
+    Period,
+    Nothing,
+}
+
+#[test]
+fn parse_sequence_variants() {
+    let call = Expression::parse(&Token::lex("print(5)")).unwrap();
+    assert_eq!(
+        call,
+        Expression::Call("print".to_string(), (), Value::Int(5), ())
+    );
+    assert_eq!(
+        call.to_string(),
+        "Expression (Call (print Value (Int (5))))"
+    );
+
+    let index = Expression::parse(&Token::lex("items[i]")).unwrap();
+    assert_eq!(
+        index,
+        Expression::Index {
+            target: "items".to_string(),
+            _open: (),
+            index: Box::new(Value::Name("i".to_string())),
+            _close: (),
+        }
+    );
+    assert_eq!(
+        index.to_string(),
+        "Expression (Index (items Value (Name (i))))"
+    );
+}
+
+#[test]
+fn sequence_variants_fall_through() {
+    assert_eq!(
+        Expression::parse(&Token::lex("print")).unwrap(),
+        Expression::Value(Value::Name("print".to_string()))
+    );
+    assert_eq!(Expression::parse(&Token::lex("print(5")), None);
+    assert_eq!(
+        Statement::parse(&Token::lex("return x")).unwrap(),
+        Statement::Return((), Expression::Value(Value::Name("x".to_string())))
+    );
+}
+
+#[test]
+fn unit_variant_matches_nothing() {
+    assert_eq!(
+        Ending::parse_one(&Token::lex(".")).unwrap().0,
+        Ending::Period
+    );
+    let tokens = Token::lex("x");
+    let (ending, rest) = Ending::parse_one(&tokens).unwrap();
+    assert_eq!(ending, Ending::Nothing);
+    assert_eq!(rest.len(), 1);
+    assert_eq!(Ending::Nothing.to_string(), "Nothing");
+}